@@ -0,0 +1,478 @@
+//! socket 压测工具：建立N个并发的客户端连接，按配置的比例发送心跳、推送与发布，统计建立连接耗时、消息往返时间的分位数以及错误数
+//!
+//! cargo run --release --bin socket_bench -- --token <access_token> --connections 1000 --duration 60 --rate 1 --mix ping=6,push=2,publish=2
+//!
+//! - ping：发送心跳，往返时间为收到pong的时间
+//! - push：向连接自己的账号发送消息，往返时间为连接收到该推送的时间（多个连接共用一个账号时推送会发给该账号的全部连接）
+//! - publish：通过http接口 /message/publish/bench-{连接序号} 发布主题消息，往返时间为订阅的连接收到该消息的时间
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use tokio::sync::{oneshot, Semaphore};
+use tokio::time::MissedTickBehavior;
+use rust_socket::client::socket_client::{SocketClient, SocketEvent, SocketEvents};
+use rust_socket::client::socket_client_builder::SocketClientBuilder;
+use rust_socket::config::ApplicationConfig;
+use rust_socket::domain::dto::socket_message::SocketMessageType;
+use rust_socket::domain::vo::RespVO;
+use rust_socket::util::error::Error;
+use rust_socket::util::result::Result;
+
+const USAGE: &str = "用法: socket_bench --token <access_token> [选项]
+
+选项:
+  --config <path>                配置文件，默认 application.yml，从中读取 socket_url、server_url 与帧格式
+  --address <addr>               socket服务地址，覆盖配置文件中的 socket_url
+  --http <addr>                  http服务地址，覆盖配置文件中的 server_url，publish 使用
+  --token <token>                认证使用的access_token，可多次指定，连接按顺序轮流使用
+  --tokens <path>                从文件读取access_token，每行一个
+  --connections <n>              并发连接数，默认100
+  --connect-concurrency <n>      同时进行握手的连接数，默认100
+  --duration <seconds>           全部连接建立后的压测时长，默认30秒
+  --rate <n>                     每个连接每秒的操作数，默认1
+  --mix <ping=6,push=2,publish=2>  各操作的比例
+  --timeout <ms>                 等待应答或消息到达的超时时间，默认5000毫秒
+  --help                         显示帮助";
+
+/// 压测的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum BenchOp {
+    Ping,
+    Push,
+    Publish,
+}
+
+impl BenchOp {
+    fn parse(name: &str) -> std::result::Result<Self, String> {
+        match name {
+            "ping" => Ok(BenchOp::Ping),
+            "push" => Ok(BenchOp::Push),
+            "publish" => Ok(BenchOp::Publish),
+            other => Err(format!("未知操作:{}，可选 ping、push、publish", other)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BenchOp::Ping => "ping",
+            BenchOp::Push => "push",
+            BenchOp::Publish => "publish",
+        }
+    }
+}
+
+/// 命令行参数
+struct BenchOptions {
+    config: String,
+    address: Option<String>,
+    http: Option<String>,
+    tokens: Vec<String>,
+    connections: usize,
+    connect_concurrency: usize,
+    duration: Duration,
+    rate: f64,
+    /// 各操作的权重
+    mix: Vec<(BenchOp, u32)>,
+    timeout: Duration,
+}
+
+impl BenchOptions {
+    fn parse(mut args: impl Iterator<Item = String>) -> std::result::Result<Self, String> {
+        let mut options = BenchOptions {
+            config: "application.yml".to_string(),
+            address: None,
+            http: None,
+            tokens: vec![],
+            connections: 100,
+            connect_concurrency: 100,
+            duration: Duration::from_secs(30),
+            rate: 1.0,
+            mix: vec![(BenchOp::Ping, 6), (BenchOp::Push, 2), (BenchOp::Publish, 2)],
+            timeout: Duration::from_millis(5000),
+        };
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or_else(|| format!("参数{}缺少值", name));
+            match arg.as_str() {
+                "--config" => options.config = value("--config")?,
+                "--address" => options.address = Some(value("--address")?),
+                "--http" => options.http = Some(value("--http")?),
+                "--token" => options.tokens.push(value("--token")?),
+                "--tokens" => {
+                    let path = value("--tokens")?;
+                    let content = std::fs::read_to_string(&path).map_err(|e| format!("读取{}失败:{}", path, e))?;
+                    options.tokens.extend(content.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from));
+                }
+                "--connections" => options.connections = BenchOptions::number(&value("--connections")?)?,
+                "--connect-concurrency" => options.connect_concurrency = BenchOptions::number(&value("--connect-concurrency")?)?,
+                "--duration" => options.duration = Duration::from_secs(BenchOptions::number(&value("--duration")?)?),
+                "--rate" => options.rate = BenchOptions::number(&value("--rate")?)?,
+                "--mix" => options.mix = BenchOptions::mix(&value("--mix")?)?,
+                "--timeout" => options.timeout = Duration::from_millis(BenchOptions::number(&value("--timeout")?)?),
+                "--help" | "-h" => return Err(String::new()),
+                other => return Err(format!("未知参数:{}", other)),
+            }
+        }
+        if options.tokens.is_empty() {
+            return Err("缺少参数--token或--tokens".to_string());
+        }
+        // NaN 与无穷大同样无法换算成操作间隔
+        if options.connections == 0 || options.connect_concurrency == 0 || !(options.rate.is_finite() && options.rate > 0.0) {
+            return Err("连接数、握手并发数以及操作频率必须大于0（操作频率须为有限数）".to_string());
+        }
+        Ok(options)
+    }
+
+    fn number<T: std::str::FromStr>(text: &str) -> std::result::Result<T, String> {
+        text.parse().map_err(|_| format!("{}不是有效的数字", text))
+    }
+
+    /// 解析 ping=6,push=2,publish=2 形式的比例
+    fn mix(text: &str) -> std::result::Result<Vec<(BenchOp, u32)>, String> {
+        let mut mix = vec![];
+        for item in text.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (name, weight) = item.split_once('=').unwrap_or((item, "1"));
+            let weight: u32 = BenchOptions::number(weight.trim())?;
+            if weight > 0 {
+                mix.push((BenchOp::parse(name.trim())?, weight));
+            }
+        }
+        if mix.is_empty() {
+            return Err(format!("操作比例{}中没有权重大于0的操作", text));
+        }
+        Ok(mix)
+    }
+
+    /// 按权重随机选择一个操作
+    fn pick(&self) -> BenchOp {
+        let total: u32 = self.mix.iter().map(|(_, weight)| weight).sum();
+        let mut point = rand::random::<u32>() % total;
+        for (op, weight) in &self.mix {
+            if point < *weight {
+                return *op;
+            }
+            point -= weight;
+        }
+        self.mix[0].0
+    }
+
+    fn uses(&self, op: BenchOp) -> bool {
+        self.mix.iter().any(|(item, _)| *item == op)
+    }
+}
+
+/// 压测结果统计
+#[derive(Default)]
+struct BenchStats {
+    /// 建立连接并完成认证的耗时
+    connect: Mutex<Vec<Duration>>,
+    /// 各操作的往返时间
+    rtt: Mutex<BTreeMap<BenchOp, Vec<Duration>>>,
+    /// 各类错误的次数
+    errors: Mutex<BTreeMap<String, u64>>,
+    /// 已完成的操作数（含失败）
+    operations: AtomicU64,
+}
+
+impl BenchStats {
+    fn record(&self, op: BenchOp, rtt: Duration) {
+        self.rtt.lock().unwrap().entry(op).or_default().push(rtt);
+    }
+
+    fn error(&self, kind: String) {
+        *self.errors.lock().unwrap().entry(kind).or_default() += 1;
+    }
+
+    fn error_count(&self) -> u64 {
+        self.errors.lock().unwrap().values().sum()
+    }
+
+    /// 打印耗时的分位数，单位：毫秒
+    fn summary(name: &str, samples: &mut [Duration]) -> String {
+        if samples.is_empty() {
+            return format!("{:<10}{:>10}", name, 0);
+        }
+        samples.sort();
+        let percentile = |p: f64| {
+            let index = ((samples.len() - 1) as f64 * p).round() as usize;
+            samples[index].as_secs_f64() * 1000.0
+        };
+        format!(
+            "{:<10}{:>10}{:>10.2}{:>10.2}{:>10.2}{:>10.2}{:>10.2}",
+            name,
+            samples.len(),
+            percentile(0.5),
+            percentile(0.9),
+            percentile(0.99),
+            percentile(0.999),
+            samples[samples.len() - 1].as_secs_f64() * 1000.0
+        )
+    }
+}
+
+/// 压测中的一个连接
+struct BenchConnection {
+    index: usize,
+    token: String,
+    client: SocketClient,
+    /// 等待到达的推送（发布）消息，key为消息中的 bench 标识，value 接收到达时间
+    arrivals: Arc<Mutex<HashMap<String, oneshot::Sender<Instant>>>>,
+    sequence: AtomicU64,
+}
+
+impl BenchConnection {
+    /// 建立连接并启动读取事件的任务
+    async fn open(index: usize, token: String, builder: SocketClientBuilder, options: &BenchOptions, stats: &Arc<BenchStats>) -> Result<Self> {
+        let start = Instant::now();
+        let (client, events) = builder.connect().await?;
+        stats.connect.lock().unwrap().push(start.elapsed());
+        let arrivals = Arc::new(Mutex::new(HashMap::new()));
+        tokio::spawn(BenchConnection::receive(events, arrivals.clone(), stats.clone()));
+        if options.uses(BenchOp::Publish) {
+            client.subscribe(&BenchConnection::topic(index)).await?;
+        }
+        Ok(Self {
+            index,
+            token,
+            client,
+            arrivals,
+            sequence: AtomicU64::new(0),
+        })
+    }
+
+    fn topic(index: usize) -> String {
+        format!("bench-{}", index)
+    }
+
+    /// 读取连接的事件，记录消息到达时间以及断开次数
+    async fn receive(mut events: SocketEvents, arrivals: Arc<Mutex<HashMap<String, oneshot::Sender<Instant>>>>, stats: Arc<BenchStats>) {
+        while let Some(event) = events.recv().await {
+            match event {
+                SocketEvent::Message(message) if message.message_type == SocketMessageType::Push => {
+                    let arrived = Instant::now();
+                    let waiter = message.payload["bench"].as_str().and_then(|id| arrivals.lock().unwrap().remove(id));
+                    if let Some(waiter) = waiter {
+                        let _ = waiter.send(arrived);
+                    }
+                }
+                SocketEvent::Disconnected { .. } => stats.error("连接断开".to_string()),
+                SocketEvent::Closed { reason } if reason != "客户端已关闭" => stats.error(format!("连接停止:{}", reason)),
+                _ => {}
+            }
+        }
+    }
+
+    /// 按固定频率执行操作直到 deadline
+    async fn run(self: Arc<Self>, options: Arc<BenchOptions>, http: Arc<reqwest::Client>, http_url: Arc<String>, stats: Arc<BenchStats>, deadline: Instant) {
+        // 频率极高时间隔可能舍入为0，而 interval 不接受0间隔
+        let period = Duration::from_secs_f64(1.0 / options.rate).max(Duration::from_micros(1));
+        // 随机错开各连接的起始时间，避免所有连接同时发送
+        let offset = period.mul_f64(rand::random::<f64>());
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + offset, period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if Instant::now() >= deadline {
+                break;
+            }
+            let op = options.pick();
+            let result = match op {
+                BenchOp::Ping => self.client.ping().await,
+                BenchOp::Push => self.push(options.timeout).await,
+                BenchOp::Publish => self.publish(&http, &http_url, options.timeout).await,
+            };
+            stats.operations.fetch_add(1, Ordering::Relaxed);
+            match result {
+                Ok(rtt) => stats.record(op, rtt),
+                Err(e) => stats.error(format!("{}失败:{}", op.name(), BenchConnection::error_kind(&e))),
+            }
+        }
+    }
+
+    /// 错误信息中去掉请求id等变化的部分，便于按类别计数
+    fn error_kind(error: &Error) -> String {
+        let text = error.to_string();
+        if text.contains("未收到应答") || text.contains("未到达") {
+            "超时".to_string()
+        } else if text.contains("连接已断开") {
+            "连接已断开".to_string()
+        } else {
+            text
+        }
+    }
+
+    /// 等待标识为 id 的消息到达，返回从 start 起的耗时
+    async fn arrival(&self, id: &str, receiver: oneshot::Receiver<Instant>, start: Instant, timeout: Duration) -> Result<Duration> {
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(arrived)) => Ok(arrived - start),
+            _ => {
+                self.arrivals.lock().unwrap().remove(id);
+                Err(Error::from(format!("消息{}未到达", id)))
+            }
+        }
+    }
+
+    fn expect(&self) -> (String, oneshot::Receiver<Instant>) {
+        let id = format!("{}-{}", self.index, self.sequence.fetch_add(1, Ordering::Relaxed));
+        let (sender, receiver) = oneshot::channel();
+        self.arrivals.lock().unwrap().insert(id.clone(), sender);
+        (id, receiver)
+    }
+
+    /// 向自己的账号发送消息，等待服务端推送回来
+    async fn push(&self, timeout: Duration) -> Result<Duration> {
+        let (id, receiver) = self.expect();
+        let start = Instant::now();
+        if let Err(e) = self.client.send(&self.client.account(), json!({ "bench": id })).await {
+            self.arrivals.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+        self.arrival(&id, receiver, start, timeout).await
+    }
+
+    /// 通过http接口向连接订阅的主题发布消息，等待消息到达
+    async fn publish(&self, http: &reqwest::Client, http_url: &str, timeout: Duration) -> Result<Duration> {
+        let (id, receiver) = self.expect();
+        let start = Instant::now();
+        let url = format!("{}/message/publish/{}", http_url, BenchConnection::topic(self.index));
+        let sent = http.post(&url).header("access_token", &self.token).json(&json!({ "bench": id })).send().await;
+        let resp: std::result::Result<RespVO<Value>, String> = match sent {
+            Ok(response) => response.json().await.map_err(|e| format!("应答格式错误:{}", e.without_url())),
+            Err(e) => Err(format!("请求失败:{}", e.without_url())),
+        };
+        let result = match resp {
+            Ok(resp) if resp.code == Some(0) => Ok(()),
+            Ok(resp) => Err(Error::from(resp.msg.unwrap_or_default())),
+            Err(e) => Err(Error::from(e)),
+        };
+        if let Err(e) = result {
+            self.arrivals.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+        self.arrival(&id, receiver, start, timeout).await
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let options = match BenchOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => Arc::new(options),
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}\n", e);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    // 指定了全部地址时不读取配置文件
+    let config = if options.address.is_none() || (options.http.is_none() && options.uses(BenchOp::Publish)) {
+        match ApplicationConfig::load(&options.config) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let http_url = match (&options.http, &config) {
+        (Some(http), _) => http.clone(),
+        (None, Some(config)) => config.server_url.replace("0.0.0.0", "127.0.0.1"),
+        (None, None) => String::new(),
+    };
+    let http_url = Arc::new(if http_url.starts_with("http") { http_url } else { format!("http://{}", http_url) });
+    let builder = |index: usize| {
+        let token = &options.tokens[index % options.tokens.len()];
+        let builder = match (&options.address, &config) {
+            (Some(address), _) => SocketClientBuilder::new(address, token),
+            (None, Some(config)) => SocketClientBuilder::from_config(config, token),
+            (None, None) => unreachable!(),
+        };
+        builder.device(&format!("bench-{}", index)).request_timeout(options.timeout)
+    };
+
+    let stats = Arc::new(BenchStats::default());
+    eprintln!("建立{}个连接...", options.connections);
+    let connect_start = Instant::now();
+    let handshakes = Arc::new(Semaphore::new(options.connect_concurrency));
+    let mut tasks = vec![];
+    for index in 0..options.connections {
+        let (builder, token) = (builder(index), options.tokens[index % options.tokens.len()].clone());
+        let (options, stats, handshakes) = (options.clone(), stats.clone(), handshakes.clone());
+        tasks.push(tokio::spawn(async move {
+            let _permit = handshakes.acquire().await.unwrap();
+            BenchConnection::open(index, token, builder, &options, &stats).await
+        }));
+    }
+    let mut connections = vec![];
+    for task in tasks {
+        match task.await {
+            Ok(Ok(connection)) => connections.push(Arc::new(connection)),
+            Ok(Err(e)) => stats.error(format!("建立连接失败:{}", e)),
+            Err(e) => stats.error(format!("建立连接失败:{}", e)),
+        }
+    }
+    eprintln!(
+        "已建立{}个连接，失败{}个，耗时{:.2}秒，开始压测{}秒...",
+        connections.len(),
+        options.connections - connections.len(),
+        connect_start.elapsed().as_secs_f64(),
+        options.duration.as_secs()
+    );
+
+    let http = Arc::new(reqwest::Client::new());
+    let run_start = Instant::now();
+    let deadline = run_start + options.duration;
+    let workers: Vec<_> = connections
+        .iter()
+        .map(|connection| {
+            tokio::spawn(connection.clone().run(options.clone(), http.clone(), http_url.clone(), stats.clone(), deadline))
+        })
+        .collect();
+    // 每5秒输出一次进度
+    let progress = {
+        let stats = stats.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                eprintln!(
+                    "[{:>4}秒] 完成操作:{}，错误:{}",
+                    run_start.elapsed().as_secs(),
+                    stats.operations.load(Ordering::Relaxed),
+                    stats.error_count()
+                );
+            }
+        })
+    };
+    futures::future::join_all(workers).await;
+    progress.abort();
+    let elapsed = run_start.elapsed();
+    for connection in &connections {
+        connection.client.close();
+    }
+
+    println!();
+    println!("连接数:{}，成功:{}，压测时长:{:.1}秒", options.connections, connections.len(), elapsed.as_secs_f64());
+    let operations = stats.operations.load(Ordering::Relaxed);
+    println!("完成操作:{}，吞吐:{:.1}次/秒", operations, operations as f64 / elapsed.as_secs_f64());
+    println!();
+    println!("{:<10}{:>10}{:>10}{:>10}{:>10}{:>10}{:>10}", "耗时(ms)", "次数", "p50", "p90", "p99", "p99.9", "max");
+    println!("{}", BenchStats::summary("connect", &mut stats.connect.lock().unwrap()));
+    for (op, samples) in stats.rtt.lock().unwrap().iter_mut() {
+        println!("{}", BenchStats::summary(op.name(), samples));
+    }
+    let errors = stats.errors.lock().unwrap();
+    println!();
+    if errors.is_empty() {
+        println!("错误:0");
+    } else {
+        println!("错误:{}", errors.values().sum::<u64>());
+        for (kind, count) in errors.iter() {
+            println!("  {:<40}{:>10}", kind, count);
+        }
+    }
+}
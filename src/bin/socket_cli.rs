@@ -0,0 +1,343 @@
+//! socket 命令行客户端：连接 socket 服务并完成认证，从标准输入（或脚本文件）逐行读取命令发送消息，同时打印服务端发来的消息
+//!
+//! cargo run --bin socket_cli -- --token <access_token> [--pretty] [--script commands.txt]
+
+use std::time::Duration;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use rust_socket::client::socket_client::{SocketClient, SocketEvent, SocketEvents};
+use rust_socket::client::socket_client_builder::SocketClientBuilder;
+use rust_socket::config::ApplicationConfig;
+use rust_socket::config::socket_codec::{FrameMode, SocketCodec};
+use rust_socket::config::socket_tls::SocketClientTls;
+use rust_socket::domain::dto::socket_message::SocketMessageDTO;
+use rust_socket::util::error::Error;
+use rust_socket::util::result::Result;
+
+const USAGE: &str = "用法: socket_cli --token <access_token> [选项]
+
+选项:
+  --config <path>     配置文件，默认 application.yml，从中读取 socket_url 与帧格式
+  --address <addr>    服务端地址，覆盖配置文件中的 socket_url
+  --line              使用换行分隔的帧格式（读取配置文件时与配置一致，否则默认为长度前缀）
+  --tls-ca <path>     通过TLS连接，使用该CA证书（PEM）校验服务端证书
+  --tls-name <name>   校验服务端证书使用的域名，默认为连接地址中的主机名
+  --device <id>       设备id
+  --tags <a,b>        连接标签，逗号分隔
+  --script <path>     从文件读取命令，读完后退出
+  --pretty            格式化输出JSON
+  --no-ack            收到推送后不自动回复ack
+  --help              显示帮助
+
+命令:
+  sub <topic>                   订阅主题
+  unsub <topic>                 取消订阅主题
+  send <account> <json>         发送消息给其他账号
+  event <name> [json]           发送业务事件
+  call <method> [json]          调用服务端方法并打印返回值
+  respond <id> [json]           应答服务端发起的request
+  ack <id>                      确认推送消息
+  ping                          发送心跳并打印往返时间
+  raw <json>                    原样发送一条消息信封
+  sleep <ms>                    等待指定毫秒数（用于脚本）
+  help                          显示命令列表
+  quit                          断开连接并退出
+以 # 开头的行为注释";
+
+/// 命令行参数
+struct CliOptions {
+    config: String,
+    address: Option<String>,
+    line: bool,
+    token: String,
+    tls_ca: Option<String>,
+    tls_name: Option<String>,
+    device: Option<String>,
+    tags: Vec<String>,
+    script: Option<String>,
+    pretty: bool,
+    auto_ack: bool,
+}
+
+impl CliOptions {
+    fn parse(mut args: impl Iterator<Item = String>) -> std::result::Result<Self, String> {
+        let mut options = CliOptions {
+            config: "application.yml".to_string(),
+            address: None,
+            line: false,
+            token: String::new(),
+            tls_ca: None,
+            tls_name: None,
+            device: None,
+            tags: vec![],
+            script: None,
+            pretty: false,
+            auto_ack: true,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or_else(|| format!("参数{}缺少值", name));
+            match arg.as_str() {
+                "--config" => options.config = value("--config")?,
+                "--address" => options.address = Some(value("--address")?),
+                "--line" => options.line = true,
+                "--token" => options.token = value("--token")?,
+                "--tls-ca" => options.tls_ca = Some(value("--tls-ca")?),
+                "--tls-name" => options.tls_name = Some(value("--tls-name")?),
+                "--device" => options.device = Some(value("--device")?),
+                "--tags" => {
+                    options.tags = value("--tags")?.split(',').map(str::trim).filter(|tag| !tag.is_empty()).map(String::from).collect()
+                }
+                "--script" => options.script = Some(value("--script")?),
+                "--pretty" => options.pretty = true,
+                "--no-ack" => options.auto_ack = false,
+                "--help" | "-h" => return Err(String::new()),
+                other => return Err(format!("未知参数:{}", other)),
+            }
+        }
+        if options.token.is_empty() {
+            return Err("缺少参数--token".to_string());
+        }
+        if options.tls_name.is_some() && options.tls_ca.is_none() {
+            return Err("参数--tls-name需要与--tls-ca一起使用".to_string());
+        }
+        Ok(options)
+    }
+
+    /// 根据参数与配置文件构造客户端，指定了 --address 时不读取配置文件
+    fn builder(&self) -> Result<SocketClientBuilder> {
+        let mut builder = match &self.address {
+            Some(address) => SocketClientBuilder::new(address, &self.token),
+            None => SocketClientBuilder::from_config(&ApplicationConfig::load(&self.config)?, &self.token),
+        };
+        if self.line {
+            builder = builder.codec(SocketCodec::new(FrameMode::Line, 65536));
+        }
+        if let Some(ca) = &self.tls_ca {
+            let tls = SocketClientTls::new(ca);
+            builder = builder.tls(match &self.tls_name {
+                Some(name) => tls.server_name(name),
+                None => tls,
+            });
+        }
+        if let Some(device) = &self.device {
+            builder = builder.device(device);
+        }
+        if !self.tags.is_empty() {
+            let tags: Vec<&str> = self.tags.iter().map(String::as_str).collect();
+            builder = builder.tags(&tags);
+        }
+        Ok(builder.auto_ack(self.auto_ack))
+    }
+}
+
+/// 一行输入对应的命令
+enum Command {
+    Subscribe(String),
+    Unsubscribe(String),
+    Send(String, Value),
+    Event(String, Value),
+    Call(String, Value),
+    Respond(String, Value),
+    Ack(String),
+    Ping,
+    Raw(SocketMessageDTO),
+    Sleep(u64),
+    Help,
+    Quit,
+}
+
+impl Command {
+    /// 解析一行输入，空行与注释返回 None
+    fn parse(line: &str) -> Result<Option<Self>> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        let (name, rest) = match line.split_once(char::is_whitespace) {
+            Some((name, rest)) => (name, rest.trim()),
+            None => (line, ""),
+        };
+        let command = match name {
+            "sub" => Command::Subscribe(Command::word(rest, "主题")?),
+            "unsub" => Command::Unsubscribe(Command::word(rest, "主题")?),
+            "send" => {
+                let (account, payload) = Command::split(rest, "接收账号")?;
+                Command::Send(account, Command::json(payload)?)
+            }
+            "event" => {
+                let (event, payload) = Command::split(rest, "事件名称")?;
+                Command::Event(event, Command::json(payload)?)
+            }
+            "call" => {
+                let (method, params) = Command::split(rest, "方法名")?;
+                Command::Call(method, Command::json(params)?)
+            }
+            "respond" => {
+                let (id, result) = Command::split(rest, "调用id")?;
+                Command::Respond(id, Command::json(result)?)
+            }
+            "ack" => Command::Ack(Command::word(rest, "消息id")?),
+            "ping" => Command::Ping,
+            "raw" => Command::Raw(SocketMessageDTO::parse(rest)?),
+            "sleep" => match rest.parse() {
+                Ok(millis) => Command::Sleep(millis),
+                Err(_) => return Err(Error::from(format!("等待时间{}不是有效的毫秒数", rest))),
+            },
+            "help" => Command::Help,
+            "quit" | "exit" => Command::Quit,
+            other => return Err(Error::from(format!("未知命令:{}，输入help查看命令列表", other))),
+        };
+        Ok(Some(command))
+    }
+
+    fn word(rest: &str, name: &str) -> Result<String> {
+        if rest.is_empty() {
+            return Err(Error::from(format!("缺少{}", name)));
+        }
+        Ok(rest.to_string())
+    }
+
+    fn split<'a>(rest: &'a str, name: &str) -> Result<(String, &'a str)> {
+        match rest.split_once(char::is_whitespace) {
+            Some((first, remain)) => Ok((first.to_string(), remain.trim())),
+            None => Ok((Command::word(rest, name)?, "")),
+        }
+    }
+
+    /// 解析JSON参数，省略时为 null
+    fn json(text: &str) -> Result<Value> {
+        if text.is_empty() {
+            return Ok(Value::Null);
+        }
+        match serde_json::from_str(text) {
+            Ok(value) => Ok(value),
+            Err(e) => Err(Error::from(format!("JSON格式错误:{}", e))),
+        }
+    }
+}
+
+/// 按 --pretty 参数输出JSON
+fn format_json(value: &impl serde::Serialize, pretty: bool) -> String {
+    let text = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    text.unwrap_or_default()
+}
+
+/// 执行一条命令，返回是否继续
+async fn execute(client: &SocketClient, command: Command, pretty: bool) -> Result<bool> {
+    match command {
+        Command::Subscribe(topic) => {
+            client.subscribe(&topic).await?;
+            eprintln!("已订阅主题:{}", topic);
+        }
+        Command::Unsubscribe(topic) => {
+            client.unsubscribe(&topic).await?;
+            eprintln!("已取消订阅主题:{}", topic);
+        }
+        Command::Send(account, payload) => {
+            let delivery = client.send(&account, payload).await?;
+            println!("{}", format_json(&delivery, pretty));
+        }
+        Command::Event(event, payload) => client.event(&event, payload)?,
+        Command::Call(method, params) => {
+            let result = client.call(&method, params).await?;
+            println!("{}", format_json(&result, pretty));
+        }
+        Command::Respond(id, result) => client.send_frame(&SocketMessageDTO::response(Some(id), &Ok(result)))?,
+        Command::Ack(id) => client.ack(&id)?,
+        Command::Ping => {
+            let rtt = client.ping().await?;
+            eprintln!("pong，往返时间:{:.2}毫秒", rtt.as_secs_f64() * 1000.0);
+        }
+        Command::Raw(message) => client.send_frame(&message)?,
+        Command::Sleep(millis) => tokio::time::sleep(Duration::from_millis(millis)).await,
+        Command::Help => eprintln!("{}", USAGE),
+        Command::Quit => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// 打印服务端发来的消息，消息输出到标准输出，连接状态输出到标准错误；客户端停止后返回
+async fn print_events(mut events: SocketEvents, pretty: bool) {
+    while let Some(event) = events.recv().await {
+        match event {
+            SocketEvent::Connected { account } => eprintln!("已连接，账号:{}", account),
+            SocketEvent::Message(message) => println!("{}", format_json(&message, pretty)),
+            SocketEvent::Disconnected { reason } => eprintln!("连接已断开:{}，正在重新连接", reason),
+            SocketEvent::Closed { reason } => eprintln!("客户端已停止:{}", reason),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let options = match CliOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}\n", e);
+            }
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let builder = match options.builder() {
+        Ok(builder) => builder,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let (client, events) = match builder.connect().await {
+        Ok(connected) => connected,
+        Err(e) => {
+            eprintln!("连接失败:{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut printer = tokio::spawn(print_events(events, options.pretty));
+    let input: Box<dyn AsyncRead + Unpin + Send> = match &options.script {
+        Some(path) => match tokio::fs::File::open(path).await {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("读取脚本{}失败:{}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(tokio::io::stdin()),
+    };
+    let mut lines = BufReader::new(input).lines();
+    loop {
+        let line = tokio::select! {
+            // 客户端停止（被踢下线、认证失败等）后退出
+            _ = &mut printer => std::process::exit(1),
+            _ = tokio::signal::ctrl_c() => break,
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("读取输入失败:{}", e);
+                    break;
+                }
+            },
+        };
+        let command = match Command::parse(&line) {
+            Ok(Some(command)) => command,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        match execute(&client, command, options.pretty).await {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    client.close();
+    let _ = tokio::time::timeout(Duration::from_secs(1), printer).await;
+}
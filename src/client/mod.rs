@@ -0,0 +1,4 @@
+//! 客户端模块
+
+pub mod socket_client;
+pub mod socket_client_builder;
@@ -0,0 +1,619 @@
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use futures::Stream;
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio_rustls::rustls::ServerName;
+use tokio_rustls::TlsConnector;
+use crate::client::socket_client_builder::SocketClientBuilder;
+use crate::config::socket_codec::SocketCodec;
+use crate::domain::dto::socket_message::{SocketMessageDTO, SocketMessageType};
+use crate::util::constant::{NOT_AUTHORIZE_CODE, TOKEN_ERROR_CODE};
+use crate::util::error::Error;
+use crate::util::result::Result;
+
+/// 心跳间隔的下限，避免配置为0时空转
+const HEARTBEAT_MIN: Duration = Duration::from_millis(100);
+
+/// socket 客户端的事件
+#[derive(Debug, Clone)]
+pub enum SocketEvent {
+    /// 已连接并完成认证（包括重新连接），account 为服务端绑定的账号
+    Connected { account: String },
+    /// 服务端发来的消息：push、request、event、kick、server_closing 以及没有对应请求的 error 等
+    Message(SocketMessageDTO),
+    /// 连接已断开，随后自动重新连接
+    Disconnected { reason: String },
+    /// 客户端已停止（主动关闭、被踢下线、认证失败或重新连接次数用尽），之后事件流结束
+    Closed { reason: String },
+}
+
+/// socket 客户端的事件流，也可以通过 StreamExt::next 读取
+pub struct SocketEvents {
+    receiver: mpsc::UnboundedReceiver<SocketEvent>,
+}
+
+impl SocketEvents {
+    /// 等待下一个事件，客户端停止后返回 None
+    pub async fn recv(&mut self) -> Option<SocketEvent> {
+        self.receiver.recv().await
+    }
+}
+
+impl Stream for SocketEvents {
+    type Item = SocketEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// 一次连接结束的方式
+enum SessionEnd {
+    /// 连接断开，需要重新连接
+    Reconnect(String),
+    /// 客户端停止，不再重新连接
+    Stop(String),
+}
+
+/// 连接任务每次等待到的事情
+enum SessionStep {
+    Close,
+    Send(Option<String>),
+    Heartbeat,
+    Frame(Result<Option<String>>),
+}
+
+/// 客户端与服务端之间的连接，明文TCP或TLS
+trait ClientIo: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ClientIo for T {}
+
+type ClientStream = Box<dyn ClientIo>;
+
+/// 客户端句柄与连接任务共享的状态
+struct ClientShared {
+    builder: SocketClientBuilder,
+    /// 启用TLS时的握手配置与校验服务端证书使用的名称
+    tls: Option<(TlsConnector, ServerName)>,
+    /// 服务端绑定的账号
+    account: Mutex<String>,
+    /// 当前连接的发送通道，未连接时为 None
+    outbound: Mutex<Option<mpsc::UnboundedSender<String>>>,
+    /// 等待服务端应答的请求，key为请求id
+    pending: Mutex<HashMap<String, oneshot::Sender<SocketMessageDTO>>>,
+    /// 已订阅的主题，重新连接后自动重新订阅
+    topics: Mutex<HashSet<String>>,
+    /// 请求id生成序列
+    sequence: AtomicU64,
+}
+
+/// socket 客户端：使用与服务端相同的帧格式与握手认证，定时发送心跳，连接断开后按指数退避自动重新连接并重新订阅主题；
+/// 句柄可以克隆后在多处使用，全部句柄释放或调用 close 后停止
+#[derive(Clone)]
+pub struct SocketClient {
+    shared: Arc<ClientShared>,
+    closer: Arc<watch::Sender<bool>>,
+}
+
+impl SocketClient {
+
+    /// 建立首次连接并启动连接任务
+    pub(crate) async fn connect(builder: SocketClientBuilder) -> Result<(SocketClient, SocketEvents)> {
+        // 证书在首次连接时读取，配置有误时直接返回错误
+        let tls = match &builder.tls {
+            Some(tls) => Some((tls.connector()?, tls.server_name_for(&builder.address)?)),
+            None => None,
+        };
+        let shared = Arc::new(ClientShared {
+            builder,
+            tls,
+            account: Mutex::new(String::new()),
+            outbound: Mutex::new(None),
+            pending: Mutex::new(HashMap::new()),
+            topics: Mutex::new(HashSet::new()),
+            sequence: AtomicU64::new(0),
+        });
+        let (stream, buffer, account) = shared.open().await?;
+        let (closer, closed) = watch::channel(false);
+        let (events, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(shared.clone().run(stream, buffer, account, closed, events));
+        Ok((SocketClient { shared, closer: Arc::new(closer) }, SocketEvents { receiver }))
+    }
+
+    /// 服务端绑定的账号
+    pub fn account(&self) -> String {
+        self.shared.account.lock().unwrap().clone()
+    }
+
+    /// 当前是否已连接
+    pub fn is_connected(&self) -> bool {
+        self.shared.outbound.lock().unwrap().is_some()
+    }
+
+    /// 已订阅的主题
+    pub fn topics(&self) -> Vec<String> {
+        let mut topics: Vec<String> = self.shared.topics.lock().unwrap().iter().cloned().collect();
+        topics.sort();
+        topics
+    }
+
+    /// 发送一条消息，不等待应答
+    pub fn send_frame(&self, message: &SocketMessageDTO) -> Result<()> {
+        self.shared.send(message.to_string())
+    }
+
+    /// 发送一条请求并等待相同id的应答（reply、error、response 或 pong），未指定id时自动分配
+    pub async fn request(&self, mut message: SocketMessageDTO) -> Result<SocketMessageDTO> {
+        let id = match message.id.clone().filter(|id| !id.is_empty()) {
+            Some(id) => id,
+            None => self.shared.next_id(),
+        };
+        message.id = Some(id.clone());
+        let (waiter, receiver) = oneshot::channel();
+        self.shared.pending.lock().unwrap().insert(id.clone(), waiter);
+        if let Err(e) = self.send_frame(&message) {
+            self.shared.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+        let timeout = self.shared.builder.request_timeout;
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(_)) => Err(Error::from(format!("连接已断开，请求{}未收到应答", id))),
+            Err(_) => {
+                self.shared.pending.lock().unwrap().remove(&id);
+                Err(Error::from(format!("请求{}在{}毫秒内未收到应答", id, timeout.as_millis())))
+            }
+        }
+    }
+
+    /// 发送心跳并返回往返时间
+    pub async fn ping(&self) -> Result<Duration> {
+        let start = Instant::now();
+        self.request(SocketMessageDTO::new(SocketMessageType::Ping, Value::Null)).await?;
+        Ok(start.elapsed())
+    }
+
+    /// 订阅主题，未连接时只记录主题，重新连接后自动订阅
+    pub async fn subscribe(&self, topic: &str) -> Result<()> {
+        self.shared.topics.lock().unwrap().insert(topic.to_string());
+        if !self.is_connected() {
+            return Ok(());
+        }
+        let result = self.topic_request(SocketMessageType::Subscribe, topic).await;
+        if result.is_err() {
+            self.shared.topics.lock().unwrap().remove(topic);
+        }
+        result
+    }
+
+    /// 取消订阅主题
+    pub async fn unsubscribe(&self, topic: &str) -> Result<()> {
+        self.shared.topics.lock().unwrap().remove(topic);
+        if !self.is_connected() {
+            return Ok(());
+        }
+        self.topic_request(SocketMessageType::Unsubscribe, topic).await
+    }
+
+    async fn topic_request(&self, message_type: SocketMessageType, topic: &str) -> Result<()> {
+        let message = SocketMessageDTO {
+            to: Some(topic.to_string()),
+            ..SocketMessageDTO::new(message_type, Value::Null)
+        };
+        self.request(message).await?.response_result()?;
+        Ok(())
+    }
+
+    /// 发送消息给其他账号，返回服务端的投递结果
+    pub async fn send(&self, to: &str, payload: Value) -> Result<Value> {
+        let message = SocketMessageDTO {
+            to: Some(to.to_string()),
+            ..SocketMessageDTO::new(SocketMessageType::Message, payload)
+        };
+        self.request(message).await?.response_result()
+    }
+
+    /// 发送业务事件，由服务端注册的 MessageHandler 处理
+    pub fn event(&self, name: &str, payload: Value) -> Result<()> {
+        let message = SocketMessageDTO {
+            topic: Some(name.to_string()),
+            ..SocketMessageDTO::new(SocketMessageType::Event, payload)
+        };
+        self.send_frame(&message)
+    }
+
+    /// 调用服务端注册的方法并等待返回值
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let message = SocketMessageDTO::request(&self.shared.next_id(), method, params);
+        self.request(message).await?.response_result()
+    }
+
+    /// 应答服务端发起的远程调用（事件流中 type 为 request 的消息）
+    pub fn respond(&self, request: &SocketMessageDTO, result: &Result<Value>) -> Result<()> {
+        self.send_frame(&SocketMessageDTO::response(request.id.clone(), result))
+    }
+
+    /// 确认收到推送消息（id 为推送消息的id），关闭自动确认时使用
+    pub fn ack(&self, id: &str) -> Result<()> {
+        let ack = SocketMessageDTO {
+            id: Some(id.to_string()),
+            ..SocketMessageDTO::new(SocketMessageType::Ack, Value::Null)
+        };
+        self.send_frame(&ack)
+    }
+
+    /// 断开连接并停止重新连接
+    pub fn close(&self) {
+        self.closer.send_replace(true);
+    }
+}
+
+impl ClientShared {
+    fn next_id(&self) -> String {
+        format!("c{}", self.sequence.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    /// 把消息交给当前连接发送
+    fn send(&self, message: String) -> Result<()> {
+        self.builder.codec.validate(&message)?;
+        match self.outbound.lock().unwrap().as_ref() {
+            Some(outbound) if outbound.send(message).is_ok() => Ok(()),
+            _ => Err(Error::from("socket连接已断开，正在重新连接")),
+        }
+    }
+
+    /// 建立连接并完成认证，返回连接、已读取但尚未处理的数据以及服务端绑定的账号
+    async fn open(&self) -> Result<(ClientStream, Vec<u8>, String)> {
+        match tokio::time::timeout(self.builder.connect_timeout, self.handshake()).await {
+            Ok(result) => result,
+            Err(_) => Err(Error::from(format!(
+                "在{}秒内未能连接{}并完成认证",
+                self.builder.connect_timeout.as_secs(),
+                self.builder.address
+            ))),
+        }
+    }
+
+    async fn handshake(&self) -> Result<(ClientStream, Vec<u8>, String)> {
+        let codec = self.builder.codec;
+        let stream = match TcpStream::connect(&self.builder.address).await {
+            Ok(stream) => stream,
+            Err(e) => return Err(Error::from(format!("连接{}失败:{}", self.builder.address, e))),
+        };
+        let _ = stream.set_nodelay(true);
+        let mut stream: ClientStream = match &self.tls {
+            Some((connector, server_name)) => match connector.connect(server_name.clone(), stream).await {
+                Ok(stream) => Box::new(stream),
+                Err(e) => return Err(Error::from(format!("与{}的TLS握手失败:{}", self.builder.address, e))),
+            },
+            None => Box::new(stream),
+        };
+        let auth = SocketMessageDTO {
+            id: Some(self.next_id()),
+            ..SocketMessageDTO::new(SocketMessageType::Auth, serde_json::to_value(&self.builder.handshake).unwrap_or_default())
+        };
+        ClientShared::write(&mut stream, &codec, &auth.to_string()).await?;
+        let mut buffer = vec![];
+        loop {
+            let frame = match ClientShared::read_frame(&mut stream, &codec, &mut buffer).await? {
+                Some(frame) => frame,
+                None => return Err(Error::from("服务端在认证完成前断开连接")),
+            };
+            let message = SocketMessageDTO::parse(&frame)?;
+            // 认证结果的 payload 为 RespVO，失败时保留服务端返回的错误码
+            if matches!(message.message_type, SocketMessageType::Reply | SocketMessageType::Error) {
+                let account = message.response_result()?;
+                return Ok((stream, buffer, account.as_str().unwrap_or_default().to_string()));
+            }
+        }
+    }
+
+    /// 连接任务：处理当前连接，断开后按退避时间重新连接，直到客户端停止
+    async fn run(
+        self: Arc<Self>,
+        mut stream: ClientStream,
+        mut buffer: Vec<u8>,
+        mut account: String,
+        closed: watch::Receiver<bool>,
+        events: mpsc::UnboundedSender<SocketEvent>,
+    ) {
+        loop {
+            *self.account.lock().unwrap() = account.clone();
+            let _ = events.send(SocketEvent::Connected { account: account.clone() });
+            let end = self.session(stream, buffer, &closed, &events).await;
+            // 连接断开后，等待应答的请求立即失败
+            *self.outbound.lock().unwrap() = None;
+            self.pending.lock().unwrap().clear();
+            let reason = match end {
+                SessionEnd::Reconnect(reason) => reason,
+                SessionEnd::Stop(reason) => {
+                    let _ = events.send(SocketEvent::Closed { reason });
+                    return;
+                }
+            };
+            log::warn!("socket客户端与{}的连接已断开:{}，准备重新连接", self.builder.address, reason);
+            let _ = events.send(SocketEvent::Disconnected { reason });
+            let mut attempts = 0;
+            loop {
+                let backoff = self.backoff(attempts);
+                attempts += 1;
+                tokio::select! {
+                    _ = ClientShared::closed(closed.clone()) => {
+                        let _ = events.send(SocketEvent::Closed { reason: "客户端已关闭".to_string() });
+                        return;
+                    }
+                    _ = tokio::time::sleep(backoff) => {}
+                }
+                match self.open().await {
+                    Ok(opened) => {
+                        (stream, buffer, account) = opened;
+                        break;
+                    }
+                    // token失效时重试没有意义
+                    Err(Error::E(message, code)) if code == NOT_AUTHORIZE_CODE || code == TOKEN_ERROR_CODE => {
+                        let _ = events.send(SocketEvent::Closed { reason: format!("认证失败:{}", message) });
+                        return;
+                    }
+                    Err(e) => {
+                        log::warn!("socket客户端第{}次重新连接{}失败:{}", attempts, self.builder.address, e);
+                        if self.builder.reconnect_attempts > 0 && attempts >= self.builder.reconnect_attempts {
+                            let reason = format!("重新连接{}次均失败:{}", attempts, e);
+                            let _ = events.send(SocketEvent::Closed { reason });
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// 第 attempts 次重新连接前的等待时间：从 reconnect_min 起翻倍，最长 reconnect_max，并随机缩短至多一半，避免大量客户端同时重连
+    fn backoff(&self, attempts: u32) -> Duration {
+        let base = self.builder.reconnect_min.saturating_mul(2u32.saturating_pow(attempts.min(16)));
+        base.min(self.builder.reconnect_max).mul_f64(0.5 + rand::random::<f64>() * 0.5)
+    }
+
+    /// 处理一次连接：发送队列中的消息、定时心跳以及接收服务端消息，返回连接结束的方式
+    async fn session(
+        &self,
+        mut stream: ClientStream,
+        mut buffer: Vec<u8>,
+        closed: &watch::Receiver<bool>,
+        events: &mpsc::UnboundedSender<SocketEvent>,
+    ) -> SessionEnd {
+        let codec = self.builder.codec;
+        let (sender, mut outbound) = mpsc::unbounded_channel();
+        *self.outbound.lock().unwrap() = Some(sender.clone());
+        // 重新订阅主题，首次连接时主题为空
+        for topic in self.topics.lock().unwrap().iter() {
+            let subscribe = SocketMessageDTO {
+                to: Some(topic.clone()),
+                ..SocketMessageDTO::new(SocketMessageType::Subscribe, Value::Null)
+            };
+            let _ = sender.send(subscribe.to_string());
+        }
+        let heartbeat = self.builder.heartbeat_interval.max(HEARTBEAT_MIN);
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + heartbeat, heartbeat);
+        let mut last_received = Instant::now();
+        let end = loop {
+            let step = tokio::select! {
+                _ = ClientShared::closed(closed.clone()) => SessionStep::Close,
+                message = outbound.recv() => SessionStep::Send(message),
+                _ = ticker.tick() => SessionStep::Heartbeat,
+                read = ClientShared::read_frame(&mut stream, &codec, &mut buffer) => SessionStep::Frame(read),
+            };
+            let frame = match step {
+                SessionStep::Close => break SessionEnd::Stop("客户端已关闭".to_string()),
+                SessionStep::Send(message) => {
+                    if let Some(message) = message {
+                        if let Err(e) = ClientShared::write(&mut stream, &codec, &message).await {
+                            break SessionEnd::Reconnect(format!("发送消息失败:{}", e));
+                        }
+                    }
+                    continue;
+                }
+                SessionStep::Heartbeat => {
+                    // 服务端空闲时也会发送ping，超过两个心跳间隔没有任何数据说明连接已失效
+                    if last_received.elapsed() >= heartbeat * 2 {
+                        break SessionEnd::Reconnect(format!("{}毫秒内未收到服务端的数据", last_received.elapsed().as_millis()));
+                    }
+                    let ping = SocketMessageDTO::new(SocketMessageType::Ping, Value::Null).to_string();
+                    if let Err(e) = ClientShared::write(&mut stream, &codec, &ping).await {
+                        break SessionEnd::Reconnect(format!("发送心跳失败:{}", e));
+                    }
+                    continue;
+                }
+                SessionStep::Frame(Ok(Some(frame))) => frame,
+                SessionStep::Frame(Ok(None)) => break SessionEnd::Reconnect("服务端断开连接".to_string()),
+                SessionStep::Frame(Err(e)) => break SessionEnd::Reconnect(format!("数据接收异常:{}", e)),
+            };
+            last_received = Instant::now();
+            let message = match SocketMessageDTO::parse(&frame) {
+                Ok(message) => message,
+                Err(e) => {
+                    log::warn!("socket客户端收到无法解析的消息:{}", e);
+                    continue;
+                }
+            };
+            if let Some(end) = self.handle(message, &sender, events) {
+                break end;
+            }
+        };
+        let _ = stream.shutdown().await;
+        end
+    }
+
+    /// 处理服务端发来的一条消息，返回 Some 表示连接需要结束
+    fn handle(&self, message: SocketMessageDTO, sender: &mpsc::UnboundedSender<String>, events: &mpsc::UnboundedSender<SocketEvent>) -> Option<SessionEnd> {
+        match message.message_type {
+            SocketMessageType::Ping => {
+                let pong = SocketMessageDTO {
+                    id: message.id,
+                    ..SocketMessageDTO::new(SocketMessageType::Pong, Value::Null)
+                };
+                let _ = sender.send(pong.to_string());
+            }
+            SocketMessageType::Pong | SocketMessageType::Reply | SocketMessageType::Error | SocketMessageType::Response => {
+                let waiter = message.id.as_ref().and_then(|id| self.pending.lock().unwrap().remove(id));
+                match waiter {
+                    Some(waiter) => {
+                        let _ = waiter.send(message);
+                    }
+                    // 没有对应请求的错误（如限速警告）交给调用方，其余（如重新订阅的应答）直接忽略
+                    None if message.message_type == SocketMessageType::Error => {
+                        let _ = events.send(SocketEvent::Message(message));
+                    }
+                    None => {}
+                }
+            }
+            SocketMessageType::Push => {
+                if self.builder.auto_ack {
+                    let ack = SocketMessageDTO {
+                        id: message.id.clone(),
+                        ..SocketMessageDTO::new(SocketMessageType::Ack, Value::Null)
+                    };
+                    let _ = sender.send(ack.to_string());
+                }
+                let _ = events.send(SocketEvent::Message(message));
+            }
+            // 被踢下线（如账号在其他设备登录）时不再重新连接，避免互相踢下线
+            SocketMessageType::Kick => {
+                let reason = format!("被服务端断开:{}", message.payload.as_str().unwrap_or_default());
+                let _ = events.send(SocketEvent::Message(message));
+                return Some(SessionEnd::Stop(reason));
+            }
+            _ => {
+                let _ = events.send(SocketEvent::Message(message));
+            }
+        }
+        None
+    }
+
+    async fn write(stream: &mut ClientStream, codec: &SocketCodec, message: &str) -> Result<()> {
+        let frame = codec.encode(message)?;
+        stream.write_all(&frame).await?;
+        Ok(())
+    }
+
+    /// 读取一个完整的帧，连接关闭时返回 None
+    async fn read_frame(stream: &mut ClientStream, codec: &SocketCodec, buffer: &mut Vec<u8>) -> Result<Option<String>> {
+        loop {
+            if let Some(frame) = codec.decode(buffer)? {
+                return Ok(Some(frame));
+            }
+            let mut buf = [0u8; 4096];
+            let size = stream.read(&mut buf).await?;
+            if size == 0 {
+                return Ok(None);
+            }
+            buffer.extend_from_slice(&buf[..size]);
+        }
+    }
+
+    /// 等待关闭信号，全部句柄释放时同样视为关闭
+    async fn closed(mut closed: watch::Receiver<bool>) {
+        while !*closed.borrow_and_update() {
+            if closed.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use serde_json::json;
+    use crate::client::socket_client::{SocketEvent, SocketEvents};
+    use crate::client::socket_client_builder::SocketClientBuilder;
+    use crate::config::socket_builder::SocketServerBuilder;
+    use crate::config::socket_server::SocketServer;
+    use crate::config::user_context::UserContext;
+    use crate::domain::dto::socket_message::SocketMessageType;
+    use crate::util::constant::NOT_AUTHORIZE_CODE;
+    use crate::util::error::Error;
+
+    async fn start() -> SocketServer {
+        SocketServerBuilder::new("127.0.0.1:0")
+            .authenticator(|handshake| async move {
+                let account = handshake.access_token.unwrap_or_default();
+                if account.is_empty() {
+                    return Err(Error::from(("access_token不能为空", NOT_AUTHORIZE_CODE)));
+                }
+                Ok(UserContext {
+                    account,
+                    name: String::new(),
+                    organize: 0,
+                    ip: String::new(),
+                    city: String::new(),
+                    leeway: 0,
+                })
+            })
+            .method("echo", |_session, params| async move { Ok(params) })
+            .start()
+            .await
+            .unwrap()
+    }
+
+    async fn next(events: &mut SocketEvents) -> SocketEvent {
+        tokio::time::timeout(Duration::from_secs(5), events.recv()).await.unwrap().unwrap()
+    }
+
+    /// 等待主题在服务端有订阅者，重新订阅是在后台完成的
+    async fn subscribed(server: &SocketServer, topic: &str) {
+        for _ in 0..100 {
+            if !server.registry().read().unwrap().subscribers(topic).is_empty() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("主题{}没有订阅者", topic);
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let server = start().await;
+        let address = server.local_addr().to_string();
+        assert!(SocketClientBuilder::new(&address, "").connect().await.is_err());
+
+        let (client, mut events) = SocketClientBuilder::new(&address, "alice")
+            .reconnect_backoff(Duration::from_millis(10), Duration::from_millis(50))
+            .connect()
+            .await
+            .unwrap();
+        assert!(matches!(next(&mut events).await, SocketEvent::Connected { account } if account == "alice"));
+        client.subscribe("news").await.unwrap();
+        assert_eq!(client.call("echo", json!("hi")).await.unwrap(), json!("hi"));
+        assert!(client.ping().await.is_ok());
+
+        // 服务端断开连接后自动重新连接，并重新订阅主题
+        let id = server.registry().read().unwrap().find_by_account("alice")[0];
+        server.registry().write().unwrap().remove(&id).unwrap().close();
+        assert!(matches!(next(&mut events).await, SocketEvent::Disconnected { .. }));
+        assert!(matches!(next(&mut events).await, SocketEvent::Connected { .. }));
+        subscribed(&server, "news").await;
+        let push = server.message(Some("news"), json!(1));
+        assert_eq!(server.publish("news", &push).unwrap(), (1, 1));
+        match next(&mut events).await {
+            SocketEvent::Message(message) => {
+                assert_eq!(message.message_type, SocketMessageType::Push);
+                assert_eq!(message.id, Some(push.id));
+            }
+            other => panic!("unexpected event:{:?}", other),
+        }
+
+        // 被踢下线后不再重新连接，事件流结束
+        assert_eq!(server.kick("alice", None, "bye").await, 1);
+        assert!(matches!(next(&mut events).await, SocketEvent::Message(message) if message.message_type == SocketMessageType::Kick));
+        assert!(matches!(next(&mut events).await, SocketEvent::Closed { .. }));
+        assert!(events.recv().await.is_none());
+        assert!(!client.is_connected());
+    }
+}
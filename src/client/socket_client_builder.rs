@@ -0,0 +1,127 @@
+use std::time::Duration;
+use crate::client::socket_client::{SocketClient, SocketEvents};
+use crate::config::ApplicationConfig;
+use crate::config::socket_codec::{FrameMode, SocketCodec};
+use crate::config::socket_tls::SocketClientTls;
+use crate::domain::dto::socket_handshake::SocketHandshakeDTO;
+use crate::util::result::Result;
+
+/// socket 客户端构造器
+#[derive(Debug, Clone)]
+pub struct SocketClientBuilder {
+    /// 服务端地址
+    pub(crate) address: String,
+    /// 握手认证信息
+    pub(crate) handshake: SocketHandshakeDTO,
+    /// 与服务端一致的帧切分方式
+    pub(crate) codec: SocketCodec,
+    /// 建立连接并完成认证的超时时间
+    pub(crate) connect_timeout: Duration,
+    /// 心跳间隔，超过两个心跳间隔未收到任何数据时视为连接已断开
+    pub(crate) heartbeat_interval: Duration,
+    /// 请求等待服务端应答的超时时间
+    pub(crate) request_timeout: Duration,
+    /// 重新连接的最短退避时间
+    pub(crate) reconnect_min: Duration,
+    /// 重新连接的最长退避时间
+    pub(crate) reconnect_max: Duration,
+    /// 连续重新连接失败多少次后放弃，0表示不放弃
+    pub(crate) reconnect_attempts: u32,
+    /// 收到推送消息后是否自动回复 ack
+    pub(crate) auto_ack: bool,
+    /// TLS配置，为空时使用明文TCP连接
+    pub(crate) tls: Option<SocketClientTls>,
+}
+
+impl SocketClientBuilder {
+    /// 连接指定地址，使用 access_token 认证，默认使用长度前缀帧、30秒心跳，断开后按1秒起翻倍、最长30秒退避重新连接
+    pub fn new(address: &str, access_token: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            handshake: SocketHandshakeDTO {
+                access_token: Some(access_token.to_string()),
+                device: None,
+                tags: None,
+            },
+            codec: SocketCodec::new(FrameMode::Length, 65536),
+            connect_timeout: Duration::from_secs(10),
+            heartbeat_interval: Duration::from_secs(30),
+            request_timeout: Duration::from_secs(10),
+            reconnect_min: Duration::from_secs(1),
+            reconnect_max: Duration::from_secs(30),
+            reconnect_attempts: 0,
+            auto_ack: true,
+            tls: None,
+        }
+    }
+
+    /// 根据 application.yml 中的配置构造，连接本机的 socket_url 并使用相同的帧切分方式
+    pub fn from_config(config: &ApplicationConfig, access_token: &str) -> Self {
+        let address = config.socket_url.replace("0.0.0.0", "127.0.0.1");
+        Self::new(&address, access_token)
+            .codec(SocketCodec::from_config(config))
+            .heartbeat_interval(Duration::from_secs(config.socket_heartbeat_interval.max(1)))
+    }
+
+    /// 设备id，同一账号在多个设备登录时用于区分连接
+    pub fn device(mut self, device: &str) -> Self {
+        self.handshake.device = Some(device.to_string());
+        self
+    }
+
+    /// 连接标签，用于分组推送
+    pub fn tags(mut self, tags: &[&str]) -> Self {
+        self.handshake.tags = Some(tags.iter().map(|tag| tag.to_string()).collect());
+        self
+    }
+
+    pub fn codec(mut self, codec: SocketCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// 重新连接的退避时间，从 min 起每次失败后翻倍，最长 max
+    pub fn reconnect_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.reconnect_min = min;
+        self.reconnect_max = max.max(min);
+        self
+    }
+
+    /// 连续重新连接失败多少次后放弃，0表示不放弃
+    pub fn reconnect_attempts(mut self, attempts: u32) -> Self {
+        self.reconnect_attempts = attempts;
+        self
+    }
+
+    /// 收到推送消息后是否自动回复 ack，关闭后需调用 SocketClient::ack 确认
+    pub fn auto_ack(mut self, enabled: bool) -> Self {
+        self.auto_ack = enabled;
+        self
+    }
+
+    /// 通过TLS连接服务端（服务端启用了 socket_tls_cert 时需要），校验服务端证书的CA需单独指定
+    pub fn tls(mut self, tls: SocketClientTls) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// 建立连接并完成认证，返回客户端句柄以及接收服务端消息的事件流；首次连接失败时直接返回错误，之后断开时自动重新连接
+    pub async fn connect(self) -> Result<(SocketClient, SocketEvents)> {
+        SocketClient::connect(self).await
+    }
+}
@@ -1,4 +1,8 @@
+use std::collections::HashMap;
 use config::{Config, File};
+use crate::config::socket_rate_limit::RateLimit;
+use crate::util::error::Error;
+use crate::util::result::Result;
 
 
 /// 配置文件 映射后的结构配置
@@ -9,6 +13,58 @@ pub struct ApplicationConfig {
     pub server_url: String,
     // socket地址
     pub socket_url: String,
+    /// socket 帧切分方式 length（长度前缀）/ line（换行分隔）
+    pub socket_frame_mode: String,
+    /// socket 单帧最大字节数
+    pub socket_max_frame_size: usize,
+    /// 每个socket连接发送队列的容量
+    pub socket_queue_capacity: usize,
+    /// 发送队列满时的策略 drop_oldest / drop_newest / disconnect
+    pub socket_overflow_policy: String,
+    /// socket连接建立后完成认证的超时时间，单位：秒
+    pub socket_auth_timeout: u64,
+    /// socket心跳间隔，空闲超过该时长的连接会收到服务端的ping，单位：秒
+    pub socket_heartbeat_interval: u64,
+    /// socket空闲超时，超过该时长未收到任何数据的连接会被断开，单位：秒
+    pub socket_idle_timeout: u64,
+    /// socket离线消息的保留时间，单位：秒
+    pub socket_offline_ttl: u64,
+    /// 每个账号最多保留的socket离线消息数
+    pub socket_offline_cap: usize,
+    /// socket推送消息等待客户端确认的超时时间，每次重发后翻倍，单位：秒
+    pub socket_ack_timeout: u64,
+    /// socket推送消息未确认时的最大重发次数，超出后转入死信
+    pub socket_ack_max_retry: u32,
+    /// 服务端远程调用socket客户端时等待应答的默认超时时间，单位：秒
+    pub socket_call_timeout: u64,
+    /// 服务停止时等待socket发送队列写完的时间，单位：秒
+    pub socket_shutdown_timeout: u64,
+    /// socket集群中当前节点的id，为空时启动时随机生成
+    pub socket_node_id: String,
+    /// socket TLS证书链（PEM）路径，为空时不启用TLS
+    pub socket_tls_cert: String,
+    /// socket TLS私钥（PEM）路径
+    pub socket_tls_key: String,
+    /// 校验客户端证书的CA证书（PEM）路径，为空时不要求客户端证书
+    pub socket_tls_client_ca: String,
+    /// socket服务（TCP与WebSocket）的最大连接数，0表示不限制
+    pub socket_max_connections: usize,
+    /// 单个IP的最大socket连接数，0表示不限制
+    pub socket_max_connections_per_ip: usize,
+    /// 允许建立socket连接的IP（CIDR），为空时允许所有IP
+    pub socket_allow_cidrs: Vec<String>,
+    /// 禁止建立socket连接的IP（CIDR），优先于 socket_allow_cidrs
+    pub socket_deny_cidrs: Vec<String>,
+    /// 每个socket连接每秒最多接收的帧数，0表示不限制
+    pub socket_rate_frames: u32,
+    /// 每个socket连接每秒最多接收的字节数，0表示不限制
+    pub socket_rate_bytes: u32,
+    /// socket连接连续超过速率限制多少次后警告客户端，0表示不警告
+    pub socket_rate_warn_after: u32,
+    /// socket连接连续超过速率限制多少次后断开连接，0表示不断开
+    pub socket_rate_disconnect_after: u32,
+    /// 按账号覆盖的socket速率限制
+    pub socket_rate_overrides: HashMap<String, RateLimit>,
     /// 主数据库地址
     pub primary_database_url: String,
     /// redis地址
@@ -31,12 +87,24 @@ pub struct ApplicationConfig {
     pub data_dir: String,
 }
 
+impl ApplicationConfig {
+    /// 加载指定路径的yml配置
+    pub fn load(path: &str) -> Result<Self> {
+        let mut config = Config::default();
+        if let Err(e) = config.merge(File::with_name(path)) {
+            return Err(Error::from(format!("读取配置文件{}失败:{}", path, e)));
+        }
+        match config.try_into() {
+            Ok(result) => Ok(result),
+            Err(e) => Err(Error::from(format!("解析配置文件{}失败:{}", path, e))),
+        }
+    }
+}
+
 impl Default for ApplicationConfig {
     /// 加载yml配置，这里还不能用log::info!进行日志打印，因为还没有初始化
     fn default() -> Self {
-        let mut config = Config::default();
-        config.merge(File::with_name("application.yml")).unwrap();
-        let result: ApplicationConfig = config.try_into().unwrap();
+        let result = ApplicationConfig::load("application.yml").unwrap();
         if result.debug {
             println!("[rust_socket] load config:{:?}", result);
             println!("[rust_socket] ///////////////////// Start On Debug Mode ////////////////////////////");
@@ -2,12 +2,15 @@ use lazy_static::lazy_static;
 use rbatis::rbatis::RBatis;
 use crate::config::redis_client::RedisClient;
 use crate::service::message_service::MessageService;
+use crate::service::socket_service::SocketService;
+use crate::service::offline_message_service::OfflineMessageService;
 use crate::config::ApplicationConfig;
 use delay_timer::prelude::{DelayTimer, DelayTimerBuilder};
 use tokio::sync::Mutex;
-use std::sync::{Arc, RwLock};
-use std::collections::HashMap;
-use crate::domain::dto::socket_client_info::SocketClientInfo;
+use std::sync::RwLock;
+use crate::config::socket_server::SocketServer;
+use crate::util::error::Error;
+use crate::util::result::Result;
 // 第一种初始化方法
 // /// CONTEXT is all of the service struct
 // pub static CONTEXT: Lazy<ServiceContext> = Lazy::new(|| ServiceContext::default());
@@ -17,7 +20,8 @@ lazy_static! {
     // CONTEXT is all of the service struct
     pub static ref CONTEXT: ServiceContext = ServiceContext::default();
     pub static ref SCHEDULER: Mutex<DelayTimer> = Mutex::new(DelayTimerBuilder::default().build());
-    pub static ref SOCKET_CLIENTS: Arc<RwLock<HashMap<u64, SocketClientInfo>>> = Arc::new(RwLock::new(HashMap::new()));
+    // 应用内运行的socket服务，启动后设置；http接口与定时任务通过 CONTEXT.socket_server() 访问
+    pub static ref SOCKET_SERVER: RwLock<Option<SocketServer>> = RwLock::new(None);
 }
 
 // 为方便使用，直接定义成宏
@@ -32,10 +36,20 @@ pub struct ServiceContext {
     pub config: ApplicationConfig,
     pub redis_client: RedisClient,
     pub primary_rbatis: RBatis,
-    pub user_service: MessageService
+    pub user_service: MessageService,
+    pub socket_service: SocketService,
+    pub offline_message_service: OfflineMessageService,
 }
 
 impl ServiceContext {
+    /// 获取应用内运行的socket服务
+    pub fn socket_server(&self) -> Result<SocketServer> {
+        match SOCKET_SERVER.read().unwrap().as_ref() {
+            Some(server) => Ok(server.clone()),
+            None => Err(Error::from("socket服务尚未启动")),
+        }
+    }
+
     /// init database pool
     pub async fn init_pool(&self) {
         // futures::executor::block_on(async {
@@ -75,6 +89,8 @@ impl Default for ServiceContext {
             primary_rbatis: crate::dao::init_rbatis(&config),
             redis_client: RedisClient::new(&config.redis_url),
             user_service: MessageService {},
+            socket_service: SocketService {},
+            offline_message_service: OfflineMessageService {},
             config,
         }
     }
@@ -7,5 +7,16 @@ pub mod redis_client;
 pub mod user_context;
 pub mod scheduler;
 pub mod socket_server;
+pub mod socket_codec;
+pub mod socket_registry;
+pub mod socket_outbound;
+pub mod socket_cluster;
+pub mod socket_dispatcher;
+pub mod socket_handler;
+pub mod socket_builder;
+pub mod socket_tls;
+pub mod socket_websocket;
+pub mod socket_guard;
+pub mod socket_rate_limit;
 pub use context::ApplicationConfig;
 pub use initializer::*;
\ No newline at end of file
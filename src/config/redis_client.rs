@@ -3,7 +3,7 @@ use std::time::Duration;
 use crate::util::error::Error;
 use crate::util::result::Result;
 use log::error;
-use redis::aio::Connection;
+use redis::aio::{Connection, PubSub};
 use redis::{AsyncCommands, RedisResult};
 
 /// Redis客户端操作工具
@@ -173,4 +173,130 @@ impl RedisClient {
             ))),
         };
     }
-}
\ No newline at end of file
+
+    /// 向列表尾部追加元素，并只保留最新的 cap 个元素，同时设置过期时间
+    pub async fn rpush_capped(&self, k: &str, v: &str, cap: usize, ex: Duration) -> Result<i64> {
+        let k = k.to_string();
+        let mut conn = self.get_conn().await?;
+        let result: RedisResult<(i64,)> = redis::pipe()
+            .atomic()
+            .cmd("RPUSH").arg(&k).arg(v)
+            .cmd("LTRIM").arg(&k).arg(-(cap.max(1) as i64)).arg(-1).ignore()
+            .cmd("EXPIRE").arg(&k).arg(ex.as_secs()).ignore()
+            .query_async(&mut conn)
+            .await;
+        return match result {
+            Ok((len,)) => Ok(len.min(cap.max(1) as i64)),
+            Err(e) => Err(Error::from(format!(
+                "RedisClient rpush_capped({}) fail:{}",
+                k,
+                e.to_string()
+            ))),
+        };
+    }
+
+    /// 获取列表中的全部元素
+    pub async fn lrange(&self, k: &str) -> Result<Vec<String>> {
+        let k = k.to_string();
+        let mut conn = self.get_conn().await?;
+        return match redis::cmd("LRANGE").arg(&k).arg(0).arg(-1).query_async(&mut conn).await {
+            Ok(v) => Ok(v),
+            Err(e) => Err(Error::from(format!(
+                "RedisClient lrange({}) fail:{}",
+                k,
+                e.to_string()
+            ))),
+        };
+    }
+
+    /// 原子地取出列表中的全部元素并删除该列表
+    pub async fn take_list(&self, k: &str) -> Result<Vec<String>> {
+        let k = k.to_string();
+        let mut conn = self.get_conn().await?;
+        let result: RedisResult<(Vec<String>,)> = redis::pipe()
+            .atomic()
+            .cmd("LRANGE").arg(&k).arg(0).arg(-1)
+            .cmd("DEL").arg(&k).ignore()
+            .query_async(&mut conn)
+            .await;
+        return match result {
+            Ok((v,)) => Ok(v),
+            Err(e) => Err(Error::from(format!(
+                "RedisClient take_list({}) fail:{}",
+                k,
+                e.to_string()
+            ))),
+        };
+    }
+
+    /// 向集合中添加元素，返回新增的元素数
+    pub async fn sadd(&self, k: &str, v: &str) -> Result<i64> {
+        let k = k.to_string();
+        let mut conn = self.get_conn().await?;
+        return match redis::cmd("SADD").arg(&k).arg(v).query_async(&mut conn).await {
+            Ok(v) => Ok(v),
+            Err(e) => Err(Error::from(format!(
+                "RedisClient sadd({}) fail:{}",
+                k,
+                e.to_string()
+            ))),
+        };
+    }
+
+    /// 从集合中移除元素，返回移除的元素数
+    pub async fn srem(&self, k: &str, v: &str) -> Result<i64> {
+        let k = k.to_string();
+        let mut conn = self.get_conn().await?;
+        return match redis::cmd("SREM").arg(&k).arg(v).query_async(&mut conn).await {
+            Ok(v) => Ok(v),
+            Err(e) => Err(Error::from(format!(
+                "RedisClient srem({}) fail:{}",
+                k,
+                e.to_string()
+            ))),
+        };
+    }
+
+    /// 获取集合中的全部元素
+    pub async fn smembers(&self, k: &str) -> Result<Vec<String>> {
+        let k = k.to_string();
+        let mut conn = self.get_conn().await?;
+        return match redis::cmd("SMEMBERS").arg(&k).query_async(&mut conn).await {
+            Ok(v) => Ok(v),
+            Err(e) => Err(Error::from(format!(
+                "RedisClient smembers({}) fail:{}",
+                k,
+                e.to_string()
+            ))),
+        };
+    }
+
+    /// 向频道发布消息，返回收到消息的订阅者数
+    pub async fn publish(&self, channel: &str, v: &str) -> Result<i64> {
+        let channel = channel.to_string();
+        let mut conn = self.get_conn().await?;
+        return match redis::cmd("PUBLISH").arg(&channel).arg(v).query_async(&mut conn).await {
+            Ok(v) => Ok(v),
+            Err(e) => Err(Error::from(format!(
+                "RedisClient publish({}) fail:{}",
+                channel,
+                e.to_string()
+            ))),
+        };
+    }
+
+    /// 订阅一组频道，返回用于接收消息的连接
+    pub async fn subscribe(&self, channels: &[String]) -> Result<PubSub> {
+        let mut pubsub = self.get_conn().await?.into_pubsub();
+        for channel in channels {
+            if let Err(e) = pubsub.subscribe(channel).await {
+                return Err(Error::from(format!(
+                    "RedisClient subscribe({}) fail:{}",
+                    channel,
+                    e.to_string()
+                )));
+            }
+        }
+        Ok(pubsub)
+    }
+}
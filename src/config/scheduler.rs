@@ -25,4 +25,12 @@ impl Scheduler {
         //scheduler.add_task(build_mysqldump_async_task().unwrap());
         info!(" - cron pool init finish!");
     }
-}
\ No newline at end of file
+
+    /// 停止调度组件（发生在系统停止时）
+    pub async fn stop_system_scheduler() {
+        if let Err(e) = SCHEDULER.lock().await.stop_delay_timer() {
+            error!("stop cron pool fail:{:?}", e);
+        }
+        info!(" - cron pool stopped!");
+    }
+}
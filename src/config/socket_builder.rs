@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use futures::FutureExt;
+use crate::config::ApplicationConfig;
+use crate::config::socket_cluster::SocketCluster;
+use crate::config::socket_codec::{FrameMode, SocketCodec};
+use crate::config::socket_handler::{MessageHandler, SocketSession};
+use crate::config::socket_outbound::OverflowPolicy;
+use crate::config::socket_rate_limit::RateLimit;
+use crate::config::socket_registry::SocketRegistry;
+use crate::config::socket_server::{SocketAuthenticator, SocketMethod, SocketServer};
+use crate::config::socket_tls::SocketTls;
+use crate::config::user_context::UserContext;
+use crate::domain::dto::socket_handshake::SocketHandshakeDTO;
+use crate::util::result::Result;
+use serde_json::Value;
+
+/// socket 服务的连接限制
+#[derive(Debug, Clone, Copy)]
+pub struct SocketLimits {
+    /// 每个连接发送队列的容量
+    pub queue_capacity: usize,
+    /// 发送队列满时的策略
+    pub overflow_policy: OverflowPolicy,
+    /// 连接建立后完成认证的超时时间
+    pub auth_timeout: Duration,
+    /// 心跳间隔，空闲超过该时长的连接会收到服务端的ping
+    pub heartbeat_interval: Duration,
+    /// 空闲超时，超过该时长未收到任何数据的连接会被断开；0表示不断开空闲连接，小于两个心跳间隔时按两个心跳间隔处理
+    pub idle_timeout: Duration,
+    /// 推送消息等待客户端确认的超时时间，每次重发后翻倍
+    pub ack_timeout: Duration,
+    /// 推送消息未确认时的最大重发次数
+    pub ack_max_retry: u32,
+    /// 远程调用等待客户端应答的默认超时时间
+    pub call_timeout: Duration,
+    /// 最大连接数，0表示不限制
+    pub max_connections: usize,
+    /// 单个IP的最大连接数，0表示不限制
+    pub max_connections_per_ip: usize,
+    /// 每个连接的入站速率限制
+    pub rate_limit: RateLimit,
+    /// 连续超过速率限制多少次后警告客户端，0表示不警告
+    pub rate_warn_after: u32,
+    /// 连续超过速率限制多少次后断开连接，0表示不断开
+    pub rate_disconnect_after: u32,
+}
+
+impl Default for SocketLimits {
+    fn default() -> Self {
+        Self {
+            queue_capacity: 1024,
+            overflow_policy: OverflowPolicy::DropOldest,
+            auth_timeout: Duration::from_secs(10),
+            heartbeat_interval: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(90),
+            ack_timeout: Duration::from_secs(10),
+            ack_max_retry: 3,
+            call_timeout: Duration::from_secs(10),
+            max_connections: 0,
+            max_connections_per_ip: 0,
+            rate_limit: RateLimit::default(),
+            rate_warn_after: 0,
+            rate_disconnect_after: 0,
+        }
+    }
+}
+
+impl SocketLimits {
+    /// 根据 application.yml 中的配置构造
+    pub fn from_config(config: &ApplicationConfig) -> Self {
+        let limits = Self {
+            queue_capacity: config.socket_queue_capacity,
+            overflow_policy: OverflowPolicy::from(config.socket_overflow_policy.as_str()),
+            auth_timeout: Duration::from_secs(config.socket_auth_timeout),
+            heartbeat_interval: Duration::from_secs(config.socket_heartbeat_interval.max(1)),
+            idle_timeout: Duration::from_secs(config.socket_idle_timeout),
+            ack_timeout: Duration::from_secs(config.socket_ack_timeout.max(1)),
+            ack_max_retry: config.socket_ack_max_retry,
+            call_timeout: Duration::from_secs(config.socket_call_timeout.max(1)),
+            max_connections: config.socket_max_connections,
+            max_connections_per_ip: config.socket_max_connections_per_ip,
+            rate_limit: RateLimit::new(config.socket_rate_frames, config.socket_rate_bytes),
+            rate_warn_after: config.socket_rate_warn_after,
+            rate_disconnect_after: config.socket_rate_disconnect_after,
+        };
+        if let Some(idle_timeout) = limits.effective_idle_timeout() {
+            if idle_timeout != limits.idle_timeout {
+                log::warn!(
+                    "socket_idle_timeout:{}秒小于两个心跳间隔，按{}秒处理",
+                    limits.idle_timeout.as_secs(),
+                    idle_timeout.as_secs()
+                );
+            }
+        }
+        limits
+    }
+
+    /// 实际生效的空闲超时：0表示不断开空闲连接；否则至少为两个心跳间隔，保证收到服务端ping的客户端有时间回复
+    pub fn effective_idle_timeout(&self) -> Option<Duration> {
+        if self.idle_timeout.is_zero() {
+            return None;
+        }
+        Some(self.idle_timeout.max(self.heartbeat_interval.max(Duration::from_secs(1)) * 2))
+    }
+}
+
+/// socket 服务构造器，一个进程内可以构造多个互不影响的服务（例如集成测试），每个服务默认使用独立的连接注册表，
+/// 启用集群时需各自注入不同节点id的 SocketCluster
+///
+/// 限制：http接口与 SocketService 只操作设置到 SOCKET_SERVER 中的服务，其他服务只能通过 start 返回的句柄访问；
+/// 离线消息（offline）与默认的token认证仍使用全局的 CONTEXT（redis与数据库），同一进程内的多个服务共享这些存储
+pub struct SocketServerBuilder {
+    pub(crate) address: String,
+    pub(crate) handlers: Vec<Arc<dyn MessageHandler>>,
+    pub(crate) codec: SocketCodec,
+    pub(crate) limits: SocketLimits,
+    pub(crate) registry: Option<Arc<RwLock<SocketRegistry>>>,
+    pub(crate) authenticator: Option<SocketAuthenticator>,
+    pub(crate) methods: HashMap<String, SocketMethod>,
+    pub(crate) cluster: Option<SocketCluster>,
+    pub(crate) offline: bool,
+    pub(crate) tls: Option<SocketTls>,
+    pub(crate) allow: Vec<String>,
+    pub(crate) deny: Vec<String>,
+    pub(crate) rate_overrides: HashMap<String, RateLimit>,
+}
+
+impl SocketServerBuilder {
+    /// 监听指定地址，默认使用长度前缀帧、默认的连接限制以及基于redis会话的token认证，不启用集群与离线消息
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+            handlers: vec![],
+            codec: SocketCodec::new(FrameMode::Length, 65536),
+            limits: SocketLimits::default(),
+            registry: None,
+            authenticator: None,
+            methods: HashMap::new(),
+            cluster: None,
+            offline: false,
+            tls: None,
+            allow: vec![],
+            deny: vec![],
+            rate_overrides: HashMap::new(),
+        }
+    }
+
+    /// 根据 application.yml 中的配置构造，启用集群与离线消息，配置了证书时启用TLS
+    pub fn from_config(config: &ApplicationConfig) -> Self {
+        let mut builder = Self::new(&config.socket_url)
+            .codec(SocketCodec::from_config(config))
+            .limits(SocketLimits::from_config(config))
+            .cluster(SocketCluster::from_config(config))
+            .offline(true);
+        builder.allow = config.socket_allow_cidrs.clone();
+        builder.deny = config.socket_deny_cidrs.clone();
+        builder.rate_overrides = config.socket_rate_overrides.clone();
+        match SocketTls::from_config(config) {
+            Some(tls) => builder.tls(tls),
+            None => builder,
+        }
+    }
+
+    /// 注册消息处理器，按注册顺序调用
+    pub fn handler<H>(mut self, handler: H) -> Self
+        where
+            H: MessageHandler + 'static,
+    {
+        self.handlers.push(Arc::new(handler));
+        self
+    }
+
+    pub fn codec(mut self, codec: SocketCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn limits(mut self, limits: SocketLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// 使用外部的连接注册表，未指定时服务使用独立的注册表
+    pub fn registry(mut self, registry: Arc<RwLock<SocketRegistry>>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// 自定义握手认证，根据握手信息返回登录用户
+    pub fn authenticator<F, Fut>(mut self, authenticator: F) -> Self
+        where
+            F: Fn(SocketHandshakeDTO) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = Result<UserContext>> + Send + 'static,
+    {
+        self.authenticator = Some(Arc::new(move |handshake| authenticator(handshake).boxed()));
+        self
+    }
+
+    /// 注册可供客户端远程调用的方法，客户端发送 topic 为方法名的 request 消息调用，返回值作为 response 应答；
+    /// 方法在独立的任务中执行，不会阻塞连接的读取
+    pub fn method<F, Fut>(mut self, name: &str, method: F) -> Self
+        where
+            F: Fn(SocketSession, Value) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = Result<Value>> + Send + 'static,
+    {
+        self.methods.insert(name.to_string(), Arc::new(move |session, params| method(session, params).boxed()));
+        self
+    }
+
+    /// 启用集群，通过该节点与其他节点互相转发推送；同一进程内的多个服务需使用不同节点id的 SocketCluster
+    pub fn cluster(mut self, cluster: SocketCluster) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// 是否通过 CONTEXT.offline_message_service 保存离线消息并在重新连接时补发
+    pub fn offline(mut self, enabled: bool) -> Self {
+        self.offline = enabled;
+        self
+    }
+
+    /// 启用TLS，客户端需先完成TLS握手再发送认证消息
+    pub fn tls(mut self, tls: SocketTls) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// 只允许来自指定IP段（CIDR）的连接，可多次调用
+    pub fn allow(mut self, cidr: &str) -> Self {
+        self.allow.push(cidr.to_string());
+        self
+    }
+
+    /// 拒绝来自指定IP段（CIDR）的连接，可多次调用，优先于 allow
+    pub fn deny(mut self, cidr: &str) -> Self {
+        self.deny.push(cidr.to_string());
+        self
+    }
+
+    /// 为指定账号设置不同于 SocketLimits::rate_limit 的速率限制
+    pub fn rate_limit(mut self, account: &str, limit: RateLimit) -> Self {
+        self.rate_overrides.insert(account.to_string(), limit);
+        self
+    }
+
+    /// 绑定地址并启动服务，返回服务句柄
+    pub async fn start(self) -> Result<SocketServer> {
+        SocketServer::start(self).await
+    }
+}
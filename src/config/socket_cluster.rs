@@ -0,0 +1,268 @@
+use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use futures::StreamExt;
+use rustflake::Snowflake;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::config::redis_client::RedisClient;
+use crate::config::ApplicationConfig;
+use crate::domain::dto::socket_message::SocketMessageDTO;
+use crate::domain::dto::socket_multicast::SocketMulticastDTO;
+use crate::domain::dto::socket_push::SocketPushDTO;
+use crate::util::constant::{SOCKET_CLUSTER_CHANNEL, SOCKET_NODE_CHANNEL_PREFIX, SOCKET_PRESENCE_PREFIX};
+use crate::util::error::Error;
+use crate::util::result::Result;
+
+/// 节点之间转发的集群事件
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClusterEvent {
+    /// 推送给指定账号
+    Push { account: String, message: SocketPushDTO },
+    /// 推送给所有连接
+    Broadcast { message: SocketPushDTO },
+    /// 按条件分组推送
+    Multicast { filter: SocketMulticastDTO, message: SocketPushDTO },
+    /// 推送给主题的订阅者
+    Publish { topic: String, message: SocketPushDTO },
+    /// 断开账号的连接，device 为空时断开该账号的全部设备
+    Kick { account: String, device: Option<String>, reason: String },
+    /// 调用账号连接上的方法，持有连接的节点把结果以 CallResult 发回 node 节点
+    Call {
+        node: String,
+        call_id: String,
+        account: String,
+        device: Option<String>,
+        method: String,
+        params: Value,
+        timeout_ms: u64,
+    },
+    /// 远程调用的结果，response 为客户端应答的信封
+    CallResult { call_id: String, response: SocketMessageDTO },
+}
+
+/// 频道中传输的消息，附带发送节点以便忽略自己发出的事件
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ClusterFrame {
+    from: String,
+    event: ClusterEvent,
+}
+
+/// socket 集群，基于redis维护 账号 -> 节点 的在线表，并通过 pub/sub 把推送转发到持有连接的节点
+///
+/// 每个节点订阅自己的私有频道 socket_node:{node_id} 以及集群广播频道 socket_cluster
+pub struct SocketCluster {
+    /// 当前节点id
+    pub node_id: String,
+    redis_client: RedisClient,
+    /// 当前节点是否正在监听集群频道
+    listening: AtomicBool,
+}
+
+impl SocketCluster {
+    pub fn new(node_id: &str, redis_client: RedisClient) -> Self {
+        Self {
+            node_id: node_id.to_string(),
+            redis_client,
+            listening: AtomicBool::new(false),
+        }
+    }
+
+    /// 根据 application.yml 中的配置构造，未配置节点id时随机生成
+    pub fn from_config(config: &ApplicationConfig) -> Self {
+        let node_id = if config.socket_node_id.is_empty() {
+            format!("node-{:08x}", rand::random::<u32>())
+        } else {
+            config.socket_node_id.clone()
+        };
+        Self::new(&node_id, RedisClient::new(&config.redis_url))
+    }
+
+    /// 当前节点的消息id生成器，worker_id 与 datacenter_id 由节点id散列得到，避免不同节点在同一毫秒生成相同的id
+    pub fn id_generator(&self) -> Snowflake {
+        let mut hasher = DefaultHasher::new();
+        self.node_id.hash(&mut hasher);
+        let hash = hasher.finish() as i64;
+        let mut generator = Snowflake::default();
+        generator.worker_id(hash & 0x1f).datacenter_id((hash >> 5) & 0x1f);
+        generator
+    }
+
+    fn presence_key(account: &str) -> String {
+        format!("{:}:{:}", SOCKET_PRESENCE_PREFIX, account)
+    }
+
+    fn node_channel(node_id: &str) -> String {
+        format!("{:}:{:}", SOCKET_NODE_CHANNEL_PREFIX, node_id)
+    }
+
+    /// 登记账号在当前节点上线
+    pub async fn join(&self, account: &str) -> Result<()> {
+        self.redis_client.sadd(&SocketCluster::presence_key(account), &self.node_id).await?;
+        Ok(())
+    }
+
+    /// 登记账号在当前节点下线
+    pub async fn leave(&self, account: &str) -> Result<()> {
+        self.redis_client.srem(&SocketCluster::presence_key(account), &self.node_id).await?;
+        Ok(())
+    }
+
+    /// 查询账号在线的全部节点
+    pub async fn nodes(&self, account: &str) -> Result<Vec<String>> {
+        self.redis_client.smembers(&SocketCluster::presence_key(account)).await
+    }
+
+    /// 把事件转发给账号在线的其他节点，返回收到事件的节点数；
+    /// 没有订阅者的节点视为已经下线，会从在线表中移除
+    pub async fn relay(&self, account: &str, event: &ClusterEvent) -> Result<u64> {
+        let frame = self.frame(event)?;
+        let mut reached = 0;
+        for node_id in self.nodes(account).await? {
+            if node_id == self.node_id {
+                continue;
+            }
+            let receivers = self.redis_client.publish(&SocketCluster::node_channel(&node_id), &frame).await?;
+            if receivers > 0 {
+                reached += 1;
+            } else {
+                log::warn!("socket节点:{} 已下线，从账号:{} 的在线表中移除", node_id, account);
+                self.redis_client.srem(&SocketCluster::presence_key(account), &node_id).await?;
+            }
+        }
+        Ok(reached)
+    }
+
+    /// 把事件发送给指定节点，返回节点是否收到
+    pub async fn send_to(&self, node_id: &str, event: &ClusterEvent) -> Result<bool> {
+        let frame = self.frame(event)?;
+        let receivers = self.redis_client.publish(&SocketCluster::node_channel(node_id), &frame).await?;
+        Ok(receivers > 0)
+    }
+
+    /// 把事件广播给集群中的其他节点，返回收到事件的节点数
+    pub async fn broadcast(&self, event: &ClusterEvent) -> Result<u64> {
+        let frame = self.frame(event)?;
+        let receivers = self.redis_client.publish(SOCKET_CLUSTER_CHANNEL, &frame).await? as u64;
+        if self.listening.load(Ordering::SeqCst) {
+            return Ok(receivers.saturating_sub(1));
+        }
+        Ok(receivers)
+    }
+
+    /// 监听其他节点转发的事件并交给 handler 处理，订阅连接断开时返回错误，由调用方决定是否重新监听
+    pub async fn listen<F, Fut>(&self, handler: F) -> Result<()>
+        where
+            F: Fn(ClusterEvent) -> Fut,
+            Fut: Future<Output = ()>,
+    {
+        let channels = vec![SocketCluster::node_channel(&self.node_id), SOCKET_CLUSTER_CHANNEL.to_string()];
+        let mut pubsub = self.redis_client.subscribe(&channels).await?;
+        self.listening.store(true, Ordering::SeqCst);
+        log::info!(" - Socket Cluster Node:   {}", self.node_id);
+        let mut messages = pubsub.on_message();
+        while let Some(message) = messages.next().await {
+            let payload: String = match message.get_payload() {
+                Ok(payload) => payload,
+                Err(e) => {
+                    log::error!("socket集群消息读取失败:{}", e);
+                    continue;
+                }
+            };
+            let frame: ClusterFrame = match serde_json::from_str(&payload) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    log::error!("socket集群消息格式错误:{}", e);
+                    continue;
+                }
+            };
+            if frame.from == self.node_id {
+                continue;
+            }
+            handler(frame.event).await;
+        }
+        self.listening.store(false, Ordering::SeqCst);
+        Err(Error::from("socket集群订阅连接已断开"))
+    }
+
+    fn frame(&self, event: &ClusterEvent) -> Result<String> {
+        let frame = ClusterFrame {
+            from: self.node_id.clone(),
+            event: event.clone(),
+        };
+        match serde_json::to_string(&frame) {
+            Ok(frame) => Ok(frame),
+            Err(e) => Err(Error::from(format!("socket集群消息序列化失败:{}", e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::Duration;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+    use crate::config::redis_client::RedisClient;
+    use crate::config::socket_cluster::{ClusterEvent, SocketCluster};
+    use crate::domain::dto::socket_push::SocketPushDTO;
+
+    const REDIS_URL: &str = "redis://127.0.0.1:6379/";
+
+    /// 两个进程内节点通过本地redis互相转发
+    #[tokio::test]
+    #[ignore = "需要本地redis，使用 cargo test -- --ignored 运行"]
+    async fn test_two_nodes() {
+        let account = "cluster_tester";
+        let node_a = SocketCluster::new("test-node-a", RedisClient::new(REDIS_URL));
+        let node_b = Arc::new(SocketCluster::new("test-node-b", RedisClient::new(REDIS_URL)));
+        node_a.redis_client.delete(&SocketCluster::presence_key(account)).await.unwrap();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let listener = node_b.clone();
+        tokio::spawn(async move {
+            let _ = listener.listen(|event| {
+                let sender = sender.clone();
+                async move {
+                    let _ = sender.send(event);
+                }
+            }).await;
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // 账号只在 node_b 在线，node_a 收到的推送转发给 node_b
+        node_b.join(account).await.unwrap();
+        let message = SocketPushDTO {
+            id: "1".to_string(),
+            seq: 0,
+            topic: None,
+            from: None,
+            payload: json!({"hello": "world"}),
+        };
+        let event = ClusterEvent::Push { account: account.to_string(), message };
+        assert_eq!(node_a.relay(account, &event).await.unwrap(), 1);
+        match tokio::time::timeout(Duration::from_secs(1), receiver.recv()).await.unwrap() {
+            Some(ClusterEvent::Push { account: target, message }) => {
+                assert_eq!(target, account);
+                assert_eq!(message.id, "1");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        // 广播频道上的事件所有节点都能收到
+        let event = ClusterEvent::Kick { account: account.to_string(), device: None, reason: "test".to_string() };
+        assert_eq!(node_a.broadcast(&event).await.unwrap(), 1);
+        assert!(matches!(
+            tokio::time::timeout(Duration::from_secs(1), receiver.recv()).await.unwrap(),
+            Some(ClusterEvent::Kick { .. })
+        ));
+
+        // 已经下线的节点会在转发时被清理
+        node_a.redis_client.sadd(&SocketCluster::presence_key(account), "test-node-gone").await.unwrap();
+        node_b.leave(account).await.unwrap();
+        assert_eq!(node_a.relay(account, &event).await.unwrap(), 0);
+        assert!(node_a.nodes(account).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::config::ApplicationConfig;
+use crate::util::constant::BAD_REQUEST_ERROR_CODE;
+use crate::util::error::Error;
+use crate::util::result::Result;
+
+/// 长度前缀模式下，帧头所占字节数（大端序u32）
+pub const FRAME_HEADER_SIZE: usize = 4;
+
+/// socket 帧的切分方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameMode {
+    /// 4字节大端序长度头 + 消息体
+    Length,
+    /// 以换行符分隔（兼容旧客户端）
+    Line,
+}
+
+impl From<&str> for FrameMode {
+    fn from(mode: &str) -> Self {
+        match mode {
+            "line" => FrameMode::Line,
+            "length" => FrameMode::Length,
+            other => {
+                log::warn!("未知的socket帧模式:{}，使用长度前缀模式(length)", other);
+                FrameMode::Length
+            }
+        }
+    }
+}
+
+/// socket 消息编解码器，负责把字节流切分成完整的帧
+#[derive(Debug, Clone, Copy)]
+pub struct SocketCodec {
+    pub mode: FrameMode,
+    /// 单帧最大字节数（不含帧头）
+    pub max_frame_size: usize,
+}
+
+impl SocketCodec {
+    pub fn new(mode: FrameMode, max_frame_size: usize) -> Self {
+        Self { mode, max_frame_size }
+    }
+
+    /// 根据 application.yml 中的配置构造编解码器
+    pub fn from_config(config: &ApplicationConfig) -> Self {
+        Self::new(FrameMode::from(config.socket_frame_mode.as_str()), config.socket_max_frame_size)
+    }
+
+    /// 校验消息能否被编码成一个帧
+    pub fn validate(&self, message: &str) -> Result<()> {
+        let body = message.as_bytes();
+        if body.len() > self.max_frame_size {
+            return Err(Error::from((
+                format!("消息长度{}超出单帧上限{}", body.len(), self.max_frame_size),
+                BAD_REQUEST_ERROR_CODE,
+            )));
+        }
+        if self.mode == FrameMode::Line && body.contains(&b'\n') {
+            return Err(Error::from(("换行分隔模式下消息不能包含换行符", BAD_REQUEST_ERROR_CODE)));
+        }
+        Ok(())
+    }
+
+    /// 将一条消息编码成待发送的帧
+    pub fn encode(&self, message: &str) -> Result<Vec<u8>> {
+        self.validate(message)?;
+        let body = message.as_bytes();
+        match self.mode {
+            FrameMode::Length => {
+                let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + body.len());
+                frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+                frame.extend_from_slice(body);
+                Ok(frame)
+            }
+            FrameMode::Line => {
+                let mut frame = Vec::with_capacity(body.len() + 1);
+                frame.extend_from_slice(body);
+                frame.push(b'\n');
+                Ok(frame)
+            }
+        }
+    }
+
+    /// 尝试从缓冲区中取出一个完整的帧，数据不足时返回 None，已取出的字节会从缓冲区中移除
+    pub fn decode(&self, buf: &mut Vec<u8>) -> Result<Option<String>> {
+        let body = match self.mode {
+            FrameMode::Length => {
+                if buf.len() < FRAME_HEADER_SIZE {
+                    return Ok(None);
+                }
+                let mut header = [0u8; FRAME_HEADER_SIZE];
+                header.copy_from_slice(&buf[..FRAME_HEADER_SIZE]);
+                let size = u32::from_be_bytes(header) as usize;
+                if size > self.max_frame_size {
+                    return Err(Error::from((
+                        format!("帧长度{}超出单帧上限{}", size, self.max_frame_size),
+                        BAD_REQUEST_ERROR_CODE,
+                    )));
+                }
+                if buf.len() < FRAME_HEADER_SIZE + size {
+                    return Ok(None);
+                }
+                let frame: Vec<u8> = buf.drain(..FRAME_HEADER_SIZE + size).collect();
+                frame[FRAME_HEADER_SIZE..].to_vec()
+            }
+            FrameMode::Line => match buf.iter().position(|b| *b == b'\n') {
+                Some(index) => {
+                    let mut frame: Vec<u8> = buf.drain(..=index).collect();
+                    frame.pop();
+                    if frame.last() == Some(&b'\r') {
+                        frame.pop();
+                    }
+                    // 一次读取到的完整行同样受单帧上限限制
+                    if frame.len() > self.max_frame_size {
+                        return Err(Error::from((
+                            format!("帧长度{}超出单帧上限{}", frame.len(), self.max_frame_size),
+                            BAD_REQUEST_ERROR_CODE,
+                        )));
+                    }
+                    frame
+                }
+                None => {
+                    // 恰好达到上限的CRLF行可能在\r之后被拆开，多留出\r的一个字节
+                    let limit = self.max_frame_size + usize::from(buf.last() == Some(&b'\r'));
+                    if buf.len() > limit {
+                        return Err(Error::from((
+                            format!("未找到换行符且数据长度超出单帧上限{}", self.max_frame_size),
+                            BAD_REQUEST_ERROR_CODE,
+                        )));
+                    }
+                    return Ok(None);
+                }
+            },
+        };
+        match String::from_utf8(body) {
+            Ok(message) => Ok(Some(message)),
+            Err(e) => Err(Error::from((format!("帧内容不是合法的UTF-8:{}", e), BAD_REQUEST_ERROR_CODE))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::socket_codec::{FrameMode, SocketCodec};
+
+    #[test]
+    fn test_length_frame() {
+        let codec = SocketCodec::new(FrameMode::Length, 1024);
+        let mut buf = codec.encode("你好，世界").unwrap();
+        buf.extend(codec.encode("second").unwrap());
+        // 模拟多字节字符被拆在两次读取之间
+        let mut stream: Vec<u8> = buf.drain(..5).collect();
+        assert_eq!(codec.decode(&mut stream).unwrap(), None);
+        stream.extend(buf);
+        assert_eq!(codec.decode(&mut stream).unwrap(), Some("你好，世界".to_string()));
+        assert_eq!(codec.decode(&mut stream).unwrap(), Some("second".to_string()));
+        assert!(stream.is_empty());
+    }
+
+    #[test]
+    fn test_line_frame() {
+        let codec = SocketCodec::new(FrameMode::Line, 1024);
+        let mut stream = b"address\r\nhello\npartial".to_vec();
+        assert_eq!(codec.decode(&mut stream).unwrap(), Some("address".to_string()));
+        assert_eq!(codec.decode(&mut stream).unwrap(), Some("hello".to_string()));
+        assert_eq!(codec.decode(&mut stream).unwrap(), None);
+        assert_eq!(stream, b"partial".to_vec());
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let codec = SocketCodec::new(FrameMode::Length, 4);
+        assert!(codec.encode("12345").is_err());
+        let mut stream = 5u32.to_be_bytes().to_vec();
+        assert!(codec.decode(&mut stream).is_err());
+        let codec = SocketCodec::new(FrameMode::Line, 4);
+        let mut stream = b"1234\r\n".to_vec();
+        assert_eq!(codec.decode(&mut stream).unwrap(), Some("1234".to_string()));
+        let mut stream = b"12345\n".to_vec();
+        assert!(codec.decode(&mut stream).is_err());
+        // 恰好达到上限的CRLF行在\r与\n之间被拆成两次读取
+        let mut stream = b"1234\r".to_vec();
+        assert_eq!(codec.decode(&mut stream).unwrap(), None);
+        stream.extend_from_slice(b"\n");
+        assert_eq!(codec.decode(&mut stream).unwrap(), Some("1234".to_string()));
+        let mut stream = b"12345".to_vec();
+        assert!(codec.decode(&mut stream).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use serde_json::Value;
+use crate::config::socket_handler::SocketSession;
+use crate::config::socket_server::SocketServer;
+use crate::domain::dto::socket_message::{SocketMessageDTO, SocketMessageType};
+use crate::domain::dto::socket_push::SocketPushDTO;
+use crate::util::constant::{BAD_REQUEST_ERROR_CODE, NOT_AUTHORIZE_CODE, NOT_EXIST_CODE, NOT_PARAMETER_CODE};
+use crate::util::error::Error;
+use crate::util::result::Result;
+
+/// socket 消息分发器，按信封的 type 把客户端发来的消息交给对应的处理函数
+pub struct SocketDispatcher {}
+
+impl SocketDispatcher {
+
+    /// 解析并处理客户端（已认证的连接）发来的一帧，格式错误或处理失败时向客户端回复 error 消息；
+    /// 消息先交给注册的 MessageHandler，没有处理器处理时再按类型交给内置的处理函数
+    pub async fn dispatch(server: &SocketServer, session: &SocketSession, frame: &str) {
+        let message = match SocketMessageDTO::parse(frame) {
+            Ok(message) => message,
+            Err(e) => {
+                log::warn!("客户端 {} 发送的消息格式错误:{}", session.id, e);
+                session.send(&SocketMessageDTO::error(None, &e));
+                return;
+            }
+        };
+        if SocketDispatcher::handle(server, session, &message) {
+            return;
+        }
+        let message_id = message.id.clone();
+        let result = match message.message_type {
+            SocketMessageType::Ping => SocketDispatcher::on_ping(session, message),
+            // 客户端对服务端心跳的应答，刷新活跃时间即可
+            SocketMessageType::Pong => Ok(()),
+            SocketMessageType::Subscribe => SocketDispatcher::on_subscribe(server, session, message, true),
+            SocketMessageType::Unsubscribe => SocketDispatcher::on_subscribe(server, session, message, false),
+            SocketMessageType::Ack => SocketDispatcher::on_ack(server, session, message),
+            SocketMessageType::Message => SocketDispatcher::on_message(server, session, message).await,
+            SocketMessageType::Request => SocketDispatcher::on_request(server, session, message),
+            SocketMessageType::Response => SocketDispatcher::on_response(server, session, message),
+            SocketMessageType::Auth => Err(Error::from(("连接已完成认证", BAD_REQUEST_ERROR_CODE))),
+            SocketMessageType::Event => Err(Error::from((
+                format!("没有处理{}事件的处理器", message.topic.unwrap_or_default()),
+                BAD_REQUEST_ERROR_CODE,
+            ))),
+            other => Err(Error::from((format!("客户端不能发送{:?}类型的消息", other), BAD_REQUEST_ERROR_CODE))),
+        };
+        if let Err(e) = result {
+            log::warn!("处理客户端 {} 的消息失败:{}", session.id, e);
+            session.send(&SocketMessageDTO::error(message_id, &e));
+        }
+    }
+
+    /// 依次交给注册的处理器，返回消息是否已被处理
+    fn handle(server: &SocketServer, session: &SocketSession, message: &SocketMessageDTO) -> bool {
+        server.handlers().iter().any(|handler| handler.on_frame(session, message))
+    }
+
+    /// 通知注册的处理器连接已完成认证
+    pub fn connect(server: &SocketServer, session: &SocketSession) {
+        for handler in server.handlers() {
+            handler.on_connect(session);
+        }
+    }
+
+    /// 通知注册的处理器连接已断开
+    pub fn disconnect(server: &SocketServer, session: &SocketSession) {
+        for handler in server.handlers() {
+            handler.on_disconnect(session);
+        }
+    }
+
+    /// 客户端发起的心跳，回复相同id的pong
+    fn on_ping(session: &SocketSession, message: SocketMessageDTO) -> Result<()> {
+        let pong = SocketMessageDTO {
+            id: message.id,
+            ..SocketMessageDTO::new(SocketMessageType::Pong, Value::Null)
+        };
+        session.send(&pong);
+        Ok(())
+    }
+
+    /// 订阅（取消订阅）主题
+    fn on_subscribe(server: &SocketServer, session: &SocketSession, message: SocketMessageDTO, subscribe: bool) -> Result<()> {
+        let topic = message.to.unwrap_or_default().trim().to_string();
+        if topic.is_empty() {
+            return Err(Error::from(("主题不能为空", NOT_PARAMETER_CODE)));
+        }
+        {
+            let registry = server.registry();
+            let mut clients = registry.write().unwrap();
+            if subscribe {
+                clients.subscribe(&session.id, &topic);
+                log::info!("客户端 {} 订阅主题:{}", session.id, topic);
+            } else {
+                clients.unsubscribe(&session.id, &topic);
+                log::info!("客户端 {} 取消订阅主题:{}", session.id, topic);
+            }
+        }
+        session.send(&SocketMessageDTO::reply(message.id, &Ok(topic)));
+        Ok(())
+    }
+
+    /// 确认收到推送消息
+    fn on_ack(server: &SocketServer, session: &SocketSession, message: SocketMessageDTO) -> Result<()> {
+        let message_id = match message.id {
+            Some(message_id) if !message_id.is_empty() => message_id,
+            _ => return Err(Error::from(("确认的消息id不能为空", NOT_PARAMETER_CODE))),
+        };
+        if let Some(client) = server.registry().read().unwrap().get(&session.id) {
+            client.ack(&message_id);
+        }
+        Ok(())
+    }
+
+    /// 把消息转发给其他账号，应答中携带投递结果；注册的处理器可以通过 authorize_message 拒绝发送
+    async fn on_message(server: &SocketServer, session: &SocketSession, message: SocketMessageDTO) -> Result<()> {
+        let to = match message.to.as_deref().map(str::trim) {
+            Some(to) if !to.is_empty() => to.to_string(),
+            _ => return Err(Error::from(("接收账号to不能为空", NOT_PARAMETER_CODE))),
+        };
+        if !server.handlers().iter().all(|handler| handler.authorize_message(session, &to)) {
+            return Err(Error::from((format!("没有向账号{}发送消息的权限", to), NOT_AUTHORIZE_CODE)));
+        }
+        let push = SocketPushDTO {
+            from: Some(session.account.clone()),
+            ..server.message(None, message.payload)
+        };
+        let delivery = server.deliver(&to, push, None).await?;
+        session.send(&SocketMessageDTO::reply(message.id, &Ok(delivery)));
+        Ok(())
+    }
+
+    /// 客户端调用服务端注册的方法，方法在独立的任务中执行，结果以相同id的 response 应答
+    fn on_request(server: &SocketServer, session: &SocketSession, message: SocketMessageDTO) -> Result<()> {
+        let call_id = match message.id {
+            Some(call_id) if !call_id.is_empty() => call_id,
+            _ => return Err(Error::from(("调用id不能为空", NOT_PARAMETER_CODE))),
+        };
+        let name = message.topic.unwrap_or_default();
+        let method = match server.method(&name) {
+            Some(method) => method,
+            None => {
+                let e = Error::from((format!("方法{}不存在", name), NOT_EXIST_CODE));
+                session.send(&SocketMessageDTO::response(Some(call_id), &Err(e)));
+                return Ok(());
+            }
+        };
+        let session = session.clone();
+        tokio::spawn(async move {
+            let result = method(session.clone(), message.payload).await;
+            if let Err(e) = &result {
+                log::warn!("客户端 {} 调用方法{}失败:{}", session.id, name, e);
+            }
+            session.send(&SocketMessageDTO::response(Some(call_id), &result));
+        });
+        Ok(())
+    }
+
+    /// 客户端应答服务端发起的远程调用，调用已超时的应答直接忽略
+    fn on_response(server: &SocketServer, session: &SocketSession, message: SocketMessageDTO) -> Result<()> {
+        let call_id = match &message.id {
+            Some(call_id) if !call_id.is_empty() => call_id.clone(),
+            _ => return Err(Error::from(("应答的调用id不能为空", NOT_PARAMETER_CODE))),
+        };
+        let answered = match server.registry().read().unwrap().get(&session.id) {
+            Some(client) => client.respond(&call_id, message.response_result()),
+            None => false,
+        };
+        if !answered {
+            log::warn!("客户端 {} 应答的调用:{} 不存在或已超时", session.id, call_id);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use ipnet::IpNet;
+use crate::util::constant::NOT_AUTHORIZE_CODE;
+use crate::util::error::Error;
+use crate::util::result::Result;
+
+/// socket 连接准入控制：全局与单个IP的连接数上限，以及IP黑白名单（CIDR）；
+/// TCP与WebSocket连接共用，从接受连接起（认证之前）即占用名额
+pub struct SocketGuard {
+    /// 全局连接数上限，0表示不限制
+    max_connections: usize,
+    /// 单个IP的连接数上限，0表示不限制
+    max_connections_per_ip: usize,
+    /// 白名单，为空时允许所有IP
+    allow: Vec<IpNet>,
+    /// 黑名单，优先于白名单
+    deny: Vec<IpNet>,
+    connections: Mutex<GuardState>,
+}
+
+#[derive(Default)]
+struct GuardState {
+    total: usize,
+    per_ip: HashMap<IpAddr, usize>,
+}
+
+/// 连接占用的名额，连接结束（drop）时归还
+pub struct ConnectionPermit {
+    guard: Arc<SocketGuard>,
+    ip: IpAddr,
+}
+
+impl SocketGuard {
+    /// allow、deny 为CIDR（如 10.0.0.0/8），也可以是单个IP
+    pub fn new(max_connections: usize, max_connections_per_ip: usize, allow: &[String], deny: &[String]) -> Result<Self> {
+        Ok(Self {
+            max_connections,
+            max_connections_per_ip,
+            allow: SocketGuard::parse(allow)?,
+            deny: SocketGuard::parse(deny)?,
+            connections: Mutex::new(GuardState::default()),
+        })
+    }
+
+    fn parse(cidrs: &[String]) -> Result<Vec<IpNet>> {
+        let mut nets = vec![];
+        for cidr in cidrs {
+            let cidr = cidr.trim();
+            let net = match cidr.parse::<IpNet>() {
+                Ok(net) => net,
+                Err(_) => match cidr.parse::<IpAddr>() {
+                    Ok(ip) => IpNet::from(ip),
+                    Err(e) => return Err(Error::from(format!("socket连接黑白名单中的{}不是有效的CIDR:{}", cidr, e))),
+                },
+            };
+            nets.push(net);
+        }
+        Ok(nets)
+    }
+
+    /// 检查是否允许该IP建立连接，允许时占用一个名额，拒绝时返回原因
+    pub fn admit(self: &Arc<Self>, ip: IpAddr) -> Result<ConnectionPermit> {
+        // IPv4映射的IPv6地址（::ffff:a.b.c.d）按IPv4处理
+        let ip = ip.to_canonical();
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return Err(Error::from((format!("IP {} 在黑名单中", ip), NOT_AUTHORIZE_CODE)));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|net| net.contains(&ip)) {
+            return Err(Error::from((format!("IP {} 不在白名单中", ip), NOT_AUTHORIZE_CODE)));
+        }
+        let mut connections = self.connections.lock().unwrap();
+        if self.max_connections > 0 && connections.total >= self.max_connections {
+            return Err(Error::from(format!("连接数已达上限{}", self.max_connections)));
+        }
+        let count = connections.per_ip.get(&ip).copied().unwrap_or_default();
+        if self.max_connections_per_ip > 0 && count >= self.max_connections_per_ip {
+            return Err(Error::from(format!("IP {} 的连接数已达上限{}", ip, self.max_connections_per_ip)));
+        }
+        connections.total += 1;
+        connections.per_ip.insert(ip, count + 1);
+        Ok(ConnectionPermit { guard: self.clone(), ip })
+    }
+
+    /// 当前占用名额的连接数
+    pub fn connections(&self) -> usize {
+        self.connections.lock().unwrap().total
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut connections = self.guard.connections.lock().unwrap();
+        connections.total = connections.total.saturating_sub(1);
+        if let Some(count) = connections.per_ip.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.per_ip.remove(&self.ip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+    use std::sync::Arc;
+    use crate::config::socket_guard::SocketGuard;
+
+    #[test]
+    fn test_admit() {
+        let allow = vec!["10.0.0.0/8".to_string(), "::1".to_string()];
+        let deny = vec!["10.0.1.0/24".to_string()];
+        let guard = Arc::new(SocketGuard::new(3, 2, &allow, &deny).unwrap());
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+        assert!(guard.admit(ip("192.168.1.1")).is_err());
+        assert!(guard.admit(ip("10.0.1.5")).is_err());
+        // IPv4映射的IPv6地址同样受黑名单限制
+        assert!(guard.admit(ip("::ffff:10.0.1.5")).is_err());
+
+        let first = guard.admit(ip("10.0.0.1")).unwrap();
+        let _second = guard.admit(ip("10.0.0.1")).unwrap();
+        assert!(guard.admit(ip("10.0.0.1")).is_err());
+        let _third = guard.admit(ip("::1")).unwrap();
+        assert!(guard.admit(ip("10.0.0.2")).is_err());
+        // 连接结束后归还名额
+        drop(first);
+        assert_eq!(guard.connections(), 2);
+        assert!(guard.admit(ip("10.0.0.1")).is_ok());
+
+        assert!(SocketGuard::new(0, 0, &["10.0.0.0/33".to_string()], &[]).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+use crate::config::socket_outbound::OutboundQueue;
+use crate::domain::dto::socket_client_info::{enqueue, SocketStats};
+use crate::domain::dto::socket_message::SocketMessageDTO;
+
+/// 已认证的socket连接会话，提供给 MessageHandler 读取连接信息并向客户端发送消息
+#[derive(Clone)]
+pub struct SocketSession {
+    /// 连接id
+    pub id: u64,
+    /// 连接绑定的账号
+    pub account: String,
+    /// 连接绑定的设备id
+    pub device: Option<String>,
+    /// 客户端地址
+    pub peer_addr: String,
+    queue: Arc<OutboundQueue>,
+    stats: Arc<SocketStats>,
+    closer: Arc<watch::Sender<bool>>,
+}
+
+impl SocketSession {
+    pub fn new(id: u64, account: &str, device: Option<&str>, peer_addr: &str, queue: Arc<OutboundQueue>, stats: Arc<SocketStats>, closer: Arc<watch::Sender<bool>>) -> Self {
+        Self {
+            id,
+            account: account.to_string(),
+            device: device.map(|item| item.to_string()),
+            peer_addr: peer_addr.to_string(),
+            queue,
+            stats,
+            closer,
+        }
+    }
+
+    /// 向客户端发送一条消息，与推送一样遵循溢出策略并计入统计，返回消息是否进入了发送队列
+    pub fn send(&self, message: &SocketMessageDTO) -> bool {
+        enqueue(self.id, &self.queue, &self.stats, &self.closer, message.to_string()).is_queued()
+    }
+
+    /// 发送完已入队的消息后断开连接
+    pub fn close(&self) {
+        self.queue.close();
+    }
+}
+
+/// socket 消息处理器，嵌入本项目时实现该trait并通过 SocketServerBuilder::handler 注册，即可处理连接事件而无需修改服务端代码
+///
+/// 回调在连接的读任务中同步执行，耗时的业务（写库、调用外部接口等）需自行 tokio::spawn 到后台执行
+pub trait MessageHandler: Send + Sync {
+    /// 连接完成认证后调用
+    fn on_connect(&self, session: &SocketSession) {}
+
+    /// 收到客户端的一条消息时调用，按注册顺序依次调用各处理器；
+    /// 返回 true 表示消息已被处理，后续处理器以及内置的处理逻辑都不再处理该消息
+    fn on_frame(&self, session: &SocketSession, message: &SocketMessageDTO) -> bool {
+        false
+    }
+
+    /// 客户端通过 message 消息向账号 to 发送消息前调用，任一处理器返回 false 时拒绝发送；
+    /// 默认允许向任意账号发送，需要限制（如只能发给好友、同组织的账号）时实现该方法
+    fn authorize_message(&self, session: &SocketSession, to: &str) -> bool {
+        true
+    }
+
+    /// 连接断开后调用
+    fn on_disconnect(&self, session: &SocketSession) {}
+}
@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio::sync::Notify;
+
+/// 发送队列已满时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// 丢弃队列中最旧的消息
+    DropOldest,
+    /// 丢弃新进入的消息
+    DropNewest,
+    /// 断开客户端连接
+    Disconnect,
+}
+
+impl From<&str> for OverflowPolicy {
+    fn from(policy: &str) -> Self {
+        match policy {
+            "drop_newest" => OverflowPolicy::DropNewest,
+            "disconnect" => OverflowPolicy::Disconnect,
+            "drop_oldest" => OverflowPolicy::DropOldest,
+            other => {
+                log::warn!("未知的发送队列溢出策略:{}，使用丢弃最旧消息(drop_oldest)", other);
+                OverflowPolicy::DropOldest
+            }
+        }
+    }
+}
+
+/// 消息入队的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnqueueResult {
+    /// 正常入队
+    Queued,
+    /// 队列已满，丢弃了最旧的一条消息后入队
+    DroppedOldest,
+    /// 队列已满，新消息被丢弃
+    DroppedNewest,
+    /// 队列已满，连接需要断开
+    Disconnect,
+    /// 队列已关闭
+    Closed,
+}
+
+impl EnqueueResult {
+    /// 新消息是否进入了队列
+    pub fn is_queued(&self) -> bool {
+        matches!(self, EnqueueResult::Queued | EnqueueResult::DroppedOldest)
+    }
+}
+
+struct QueueState {
+    messages: VecDeque<String>,
+    closed: bool,
+}
+
+/// 单个连接的有界发送队列，由该连接的写任务独占消费
+pub struct OutboundQueue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+impl OutboundQueue {
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                messages: VecDeque::new(),
+                closed: false,
+            }),
+            notify: Notify::new(),
+            capacity: capacity.max(1),
+            policy,
+        }
+    }
+
+    /// 将消息放入队列，不会阻塞
+    pub fn push(&self, message: String) -> EnqueueResult {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return EnqueueResult::Closed;
+        }
+        let mut result = EnqueueResult::Queued;
+        if state.messages.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    state.messages.pop_front();
+                    result = EnqueueResult::DroppedOldest;
+                }
+                OverflowPolicy::DropNewest => return EnqueueResult::DroppedNewest,
+                OverflowPolicy::Disconnect => {
+                    state.messages.clear();
+                    state.closed = true;
+                    drop(state);
+                    self.notify.notify_one();
+                    return EnqueueResult::Disconnect;
+                }
+            }
+        }
+        state.messages.push_back(message);
+        drop(state);
+        self.notify.notify_one();
+        result
+    }
+
+    /// 取出下一条待发送的消息，队列关闭且已清空时返回 None
+    pub async fn pop(&self) -> Option<String> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(message) = state.messages.pop_front() {
+                    return Some(message);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// 关闭队列，已入队的消息仍会被写任务发送完
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.notify.notify_one();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::socket_outbound::{EnqueueResult, OutboundQueue, OverflowPolicy};
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let queue = OutboundQueue::new(2, OverflowPolicy::DropOldest);
+        assert_eq!(queue.push("1".to_string()), EnqueueResult::Queued);
+        assert_eq!(queue.push("2".to_string()), EnqueueResult::Queued);
+        assert_eq!(queue.push("3".to_string()), EnqueueResult::DroppedOldest);
+        queue.close();
+        assert_eq!(queue.pop().await, Some("2".to_string()));
+        assert_eq!(queue.pop().await, Some("3".to_string()));
+        assert_eq!(queue.pop().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_drop_newest_and_disconnect() {
+        let queue = OutboundQueue::new(1, OverflowPolicy::DropNewest);
+        queue.push("1".to_string());
+        assert_eq!(queue.push("2".to_string()), EnqueueResult::DroppedNewest);
+        assert_eq!(queue.pop().await, Some("1".to_string()));
+
+        let queue = OutboundQueue::new(1, OverflowPolicy::Disconnect);
+        queue.push("1".to_string());
+        assert_eq!(queue.push("2".to_string()), EnqueueResult::Disconnect);
+        assert!(queue.is_closed());
+        assert_eq!(queue.pop().await, None);
+        assert_eq!(queue.push("3".to_string()), EnqueueResult::Closed);
+    }
+}
@@ -0,0 +1,169 @@
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+/// 连续超限的计数在该时长内没有新的超限后清零
+const VIOLATION_RESET: Duration = Duration::from_secs(10);
+
+/// socket 连接的入站速率限制，0表示不限制
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// 每秒最多接收的帧数
+    #[serde(default)]
+    pub frames_per_second: u32,
+    /// 每秒最多接收的字节数
+    #[serde(default)]
+    pub bytes_per_second: u32,
+}
+
+impl RateLimit {
+    pub fn new(frames_per_second: u32, bytes_per_second: u32) -> Self {
+        Self {
+            frames_per_second,
+            bytes_per_second,
+        }
+    }
+}
+
+/// 令牌桶，容量为一秒的配额；令牌不足时允许透支，透支的部分需要等待补充
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u32, now: Instant) -> Option<Self> {
+        if rate == 0 {
+            return None;
+        }
+        Some(Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last: now,
+        })
+    }
+
+    /// 取出n个令牌，返回需要等待多久才能补足透支的令牌
+    fn take(&mut self, n: f64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last = now;
+        self.tokens -= n;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// 收到一帧后的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    /// 未超限，立即处理
+    Allow,
+    /// 超限，等待指定时长后再处理（暂停读取，由TCP背压限制客户端）
+    Throttle(Duration),
+    /// 连续超限达到警告次数，通知客户端后等待指定时长再处理
+    Warn(Duration),
+    /// 连续超限达到断开次数，断开连接
+    Disconnect,
+}
+
+/// 单个连接的入站限速器：超限先限速，连续超限达到 warn_after 次时警告，达到 disconnect_after 次时断开
+pub struct RateLimiter {
+    frames: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+    /// 连续超限多少次后警告，0表示不警告
+    warn_after: u32,
+    /// 连续超限多少次后断开连接，0表示不断开
+    disconnect_after: u32,
+    violations: u32,
+    last_violation: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit, warn_after: u32, disconnect_after: u32) -> Self {
+        let now = Instant::now();
+        Self {
+            frames: TokenBucket::new(limit.frames_per_second, now),
+            bytes: TokenBucket::new(limit.bytes_per_second, now),
+            warn_after,
+            disconnect_after,
+            violations: 0,
+            last_violation: None,
+        }
+    }
+
+    /// 记录收到的一帧（size 为字节数），返回处理方式
+    pub fn check(&mut self, size: usize) -> RateDecision {
+        self.check_at(size, Instant::now())
+    }
+
+    fn check_at(&mut self, size: usize, now: Instant) -> RateDecision {
+        let mut wait = Duration::ZERO;
+        if let Some(frames) = &mut self.frames {
+            wait = wait.max(frames.take(1.0, now));
+        }
+        if let Some(bytes) = &mut self.bytes {
+            wait = wait.max(bytes.take(size as f64, now));
+        }
+        if wait.is_zero() {
+            return RateDecision::Allow;
+        }
+        if let Some(last) = self.last_violation {
+            if now.saturating_duration_since(last) >= VIOLATION_RESET {
+                self.violations = 0;
+            }
+        }
+        self.violations += 1;
+        self.last_violation = Some(now);
+        if self.disconnect_after > 0 && self.violations >= self.disconnect_after {
+            RateDecision::Disconnect
+        } else if self.warn_after > 0 && self.violations == self.warn_after {
+            RateDecision::Warn(wait)
+        } else {
+            RateDecision::Throttle(wait)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+    use crate::config::socket_rate_limit::{RateDecision, RateLimit, RateLimiter};
+
+    #[test]
+    fn test_frames() {
+        let mut limiter = RateLimiter::new(RateLimit::new(10, 0), 2, 3);
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert_eq!(limiter.check_at(1, now), RateDecision::Allow);
+        }
+        // 配额用完后按透支的令牌等待，连续超限逐步升级为警告、断开
+        assert_eq!(limiter.check_at(1, now), RateDecision::Throttle(Duration::from_millis(100)));
+        assert_eq!(limiter.check_at(1, now), RateDecision::Warn(Duration::from_millis(200)));
+        assert_eq!(limiter.check_at(1, now), RateDecision::Disconnect);
+
+        // 一段时间没有超限后重新计数
+        let later = now + Duration::from_secs(11);
+        assert_eq!(limiter.check_at(1, later), RateDecision::Allow);
+        let mut decision = RateDecision::Allow;
+        while decision == RateDecision::Allow {
+            decision = limiter.check_at(1, later);
+        }
+        assert!(matches!(decision, RateDecision::Throttle(_)));
+    }
+
+    #[test]
+    fn test_bytes() {
+        let mut limiter = RateLimiter::new(RateLimit::new(0, 1000), 0, 0);
+        let now = Instant::now();
+        // 超过一秒配额的大帧也能通过，但需要等待补足透支的部分
+        assert_eq!(limiter.check_at(1500, now), RateDecision::Throttle(Duration::from_millis(500)));
+        assert_eq!(limiter.check_at(500, now + Duration::from_secs(1)), RateDecision::Allow);
+        // 不限制时始终允许
+        let mut unlimited = RateLimiter::new(RateLimit::default(), 1, 1);
+        assert_eq!(unlimited.check_at(usize::MAX, now), RateDecision::Allow);
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::{HashMap, HashSet};
+use tokio::sync::oneshot;
+use crate::domain::dto::socket_client_info::SocketClientInfo;
+use crate::domain::dto::socket_push::SocketPushDTO;
+
+/// 未指定设备id时使用的默认设备
+pub const DEFAULT_DEVICE: &str = "";
+
+/// socket 客户端注册表，按连接id保存连接，并维护 账号 -> 设备 -> 连接id 以及 主题 -> 连接id 的索引
+#[derive(Default)]
+pub struct SocketRegistry {
+    /// 连接id生成序列
+    sequence: u64,
+    /// 所有已建立的连接
+    clients: HashMap<u64, SocketClientInfo>,
+    /// 账号 -> 设备id -> 连接id
+    accounts: HashMap<String, HashMap<String, u64>>,
+    /// 主题 -> 订阅该主题的连接id
+    topics: HashMap<String, HashSet<u64>>,
+}
+
+impl SocketRegistry {
+    /// 分配一个新的连接id
+    pub fn next_id(&mut self) -> u64 {
+        self.sequence += 1;
+        self.sequence
+    }
+
+    pub fn insert(&mut self, client: SocketClientInfo) {
+        self.clients.insert(client.id, client);
+    }
+
+    pub fn get(&self, id: &u64) -> Option<&SocketClientInfo> {
+        self.clients.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: &u64) -> Option<&mut SocketClientInfo> {
+        self.clients.get_mut(id)
+    }
+
+    /// 遍历所有连接
+    pub fn values(&self) -> impl Iterator<Item = &SocketClientInfo> {
+        self.clients.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// 将连接绑定到账号（及设备），返回被替换的同一账号同一设备的旧连接id；
+    /// 旧连接不再出现在账号索引中，但仍保留在注册表里，由调用方通知客户端后断开
+    pub fn bind(&mut self, id: u64, account: &str, device: Option<&str>) -> Option<u64> {
+        if !self.clients.contains_key(&id) {
+            return None;
+        }
+        // 一个连接只能绑定一次，重复握手时先解除原有的绑定
+        self.unbind(id);
+        let device_key = device.unwrap_or(DEFAULT_DEVICE).to_string();
+        let replaced = self
+            .accounts
+            .entry(account.to_string())
+            .or_default()
+            .insert(device_key, id)
+            .filter(|old| *old != id);
+        let client = self.clients.get_mut(&id).unwrap();
+        client.account = Some(account.to_string());
+        client.device = device.map(|item| item.to_string());
+        replaced
+    }
+
+    /// 解除连接与账号的绑定
+    fn unbind(&mut self, id: u64) {
+        let client = match self.clients.get(&id) {
+            Some(client) => client,
+            None => return,
+        };
+        let account = match &client.account {
+            Some(account) => account.clone(),
+            None => return,
+        };
+        let device_key = client.device.clone().unwrap_or_else(|| DEFAULT_DEVICE.to_string());
+        if let Some(devices) = self.accounts.get_mut(&account) {
+            if devices.get(&device_key) == Some(&id) {
+                devices.remove(&device_key);
+            }
+            if devices.is_empty() {
+                self.accounts.remove(&account);
+            }
+        }
+    }
+
+    /// 移除连接，并清理账号及主题索引
+    pub fn remove(&mut self, id: &u64) -> Option<SocketClientInfo> {
+        self.unbind(*id);
+        let client = self.clients.remove(id)?;
+        for topic in &client.topics {
+            self.remove_subscriber(topic, id);
+        }
+        Some(client)
+    }
+
+    /// 连接订阅主题，返回是否为新增的订阅
+    pub fn subscribe(&mut self, id: &u64, topic: &str) -> bool {
+        let client = match self.clients.get_mut(id) {
+            Some(client) => client,
+            None => return false,
+        };
+        if !client.topics.insert(topic.to_string()) {
+            return false;
+        }
+        self.topics.entry(topic.to_string()).or_default().insert(*id);
+        true
+    }
+
+    /// 连接取消订阅主题，返回该连接之前是否订阅过
+    pub fn unsubscribe(&mut self, id: &u64, topic: &str) -> bool {
+        let removed = match self.clients.get_mut(id) {
+            Some(client) => client.topics.remove(topic),
+            None => false,
+        };
+        if removed {
+            self.remove_subscriber(topic, id);
+        }
+        removed
+    }
+
+    fn remove_subscriber(&mut self, topic: &str, id: &u64) {
+        if let Some(subscribers) = self.topics.get_mut(topic) {
+            subscribers.remove(id);
+            if subscribers.is_empty() {
+                self.topics.remove(topic);
+            }
+        }
+    }
+
+    /// 查找订阅了主题的所有连接id
+    pub fn subscribers(&self, topic: &str) -> Vec<u64> {
+        match self.topics.get(topic) {
+            Some(subscribers) => subscribers.iter().copied().collect(),
+            None => vec![],
+        }
+    }
+
+    /// 根据账号查找其所有设备上的连接id
+    pub fn find_by_account(&self, account: &str) -> Vec<u64> {
+        match self.accounts.get(account) {
+            Some(devices) => devices.values().copied().collect(),
+            None => vec![],
+        }
+    }
+
+    /// 根据账号和设备查找连接id
+    pub fn find_by_device(&self, account: &str, device: &str) -> Option<u64> {
+        self.accounts.get(account).and_then(|devices| devices.get(device).copied())
+    }
+
+    /// 查找远程调用的目标连接：指定设备时为该设备的连接，否则为账号最近建立的连接
+    pub fn find_call_target(&self, account: &str, device: Option<&str>) -> Option<u64> {
+        match device {
+            Some(device) => self.find_by_device(account, device),
+            None => self.find_by_account(account).into_iter().max(),
+        }
+    }
+
+    /// 刷新连接的最后活跃时间
+    pub fn touch(&self, id: &u64) {
+        if let Some(client) = self.clients.get(id) {
+            client.touch();
+        }
+    }
+
+    /// 向账号的所有连接推送一条消息，返回（成功进入发送队列的连接数，每个连接确认结果的接收端），wait_ack 为 false 时不返回接收端
+    pub fn send_to_account(&self, account: &str, message: &SocketPushDTO, wait_ack: bool) -> (u64, Vec<oneshot::Receiver<bool>>) {
+        let mut queued = 0;
+        let mut receivers = vec![];
+        for id in self.find_by_account(account) {
+            if let Some(client) = self.clients.get(&id) {
+                let (waiter, receiver) = if wait_ack {
+                    let (waiter, receiver) = oneshot::channel();
+                    (Some(waiter), Some(receiver))
+                } else {
+                    (None, None)
+                };
+                if client.push(message, waiter).is_queued() {
+                    queued += 1;
+                    receivers.extend(receiver);
+                }
+            }
+        }
+        (queued, receivers)
+    }
+
+    /// 向所有满足条件的连接推送一条消息，返回（匹配的连接数，成功进入发送队列的连接数）
+    pub fn send_matching<F>(&self, message: &SocketPushDTO, filter: F) -> (u64, u64)
+        where
+            F: Fn(&SocketClientInfo) -> bool,
+    {
+        let mut matched = 0;
+        let mut queued = 0;
+        for client in self.clients.values().filter(|client| filter(client)) {
+            matched += 1;
+            if client.push_untracked(message).is_queued() {
+                queued += 1;
+            }
+        }
+        (matched, queued)
+    }
+
+    /// 向订阅了主题的所有连接推送一条消息，返回（订阅的连接数，成功进入发送队列的连接数）
+    pub fn publish(&self, topic: &str, message: &SocketPushDTO) -> (u64, u64) {
+        let mut matched = 0;
+        let mut queued = 0;
+        for id in self.subscribers(topic) {
+            if let Some(client) = self.clients.get(&id) {
+                matched += 1;
+                if client.push_untracked(message).is_queued() {
+                    queued += 1;
+                }
+            }
+        }
+        (matched, queued)
+    }
+}
@@ -1,97 +1,1417 @@
-use std::collections::HashMap;
-use std::net::Shutdown;
-use async_std::net::{TcpListener, TcpStream};
-use async_std::prelude::*;
-use std::sync::{Arc, RwLock};
-use tokio::io::AsyncWriteExt;
-use crate::config::SOCKET_CLIENTS;
-use crate::domain::dto::socket_client_info::SocketClientInfo;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use futures::future::{select_ok, BoxFuture};
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{oneshot, watch};
+use tokio_rustls::TlsAcceptor;
+use rustflake::Snowflake;
+use crate::config::CONTEXT;
+use crate::config::socket_builder::{SocketLimits, SocketServerBuilder};
+use crate::config::socket_cluster::{ClusterEvent, SocketCluster};
+use crate::config::socket_codec::SocketCodec;
+use crate::config::socket_dispatcher::SocketDispatcher;
+use crate::config::socket_guard::{ConnectionPermit, SocketGuard};
+use crate::config::socket_handler::{MessageHandler, SocketSession};
+use crate::config::socket_outbound::OutboundQueue;
+use crate::config::socket_rate_limit::{RateDecision, RateLimit, RateLimiter};
+use crate::config::socket_registry::{SocketRegistry, DEFAULT_DEVICE};
+use crate::config::user_context::UserContext;
+use crate::domain::dto::socket_client_info::{SocketClientInfo, SocketStats};
+use crate::domain::dto::socket_handshake::SocketHandshakeDTO;
+use crate::domain::dto::socket_message::{SocketMessageDTO, SocketMessageType};
+use crate::domain::dto::socket_push::SocketPushDTO;
+use crate::domain::vo::socket::{DeliveryState, SocketDeliveryVO};
+use crate::util::constant::{BAD_REQUEST_ERROR_CODE, NOT_AUTHORIZE_CODE, NOT_EXIST_CODE};
+use crate::util::error::Error;
+use crate::util::date_time::DateUtils;
+use crate::util::result::Result;
 
-pub struct SocketServer {}
+/// 接受连接失败后的最短退避时间
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(100);
+/// 接受连接失败后的最长退避时间
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(5);
 
+/// 断开连接前等待写任务发送 kick 消息的最长时间
+const KICK_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 离线消息补发锁的分段数，按账号散列到其中一段
+const OFFLINE_GATE_STRIPES: usize = 64;
+
+/// 同一账号同一设备在新连接上登录时，告知旧连接的断开原因
+const REPLACED_REASON: &str = "账号在其他设备登录";
+
+/// 握手认证函数，根据握手信息返回登录用户
+pub type SocketAuthenticator = Arc<dyn Fn(SocketHandshakeDTO) -> BoxFuture<'static, Result<UserContext>> + Send + Sync>;
+
+/// 客户端可以远程调用的服务端方法，参数为调用方的连接会话以及调用参数
+pub type SocketMethod = Arc<dyn Fn(SocketSession, Value) -> BoxFuture<'static, Result<Value>> + Send + Sync>;
+
+struct SocketServerState {
+    local_addr: SocketAddr,
+    registry: Arc<RwLock<SocketRegistry>>,
+    handlers: Vec<Arc<dyn MessageHandler>>,
+    codec: SocketCodec,
+    limits: SocketLimits,
+    authenticator: SocketAuthenticator,
+    /// 客户端可以远程调用的方法，key为方法名
+    methods: HashMap<String, SocketMethod>,
+    /// 经集群转发、等待其他节点返回结果的远程调用，key为调用id
+    calls: Mutex<HashMap<String, oneshot::Sender<Result<Value>>>>,
+    /// 集群节点，未启用集群转发时为 None
+    cluster: Option<SocketCluster>,
+    /// 推送消息与远程调用的id生成器，启用集群时按节点id区分
+    ids: Mutex<Snowflake>,
+    /// 是否启用离线消息
+    offline: bool,
+    /// 离线消息补发锁：补发并绑定账号时持有写锁，投递时持有读锁，避免投递方在补发与绑定之间把消息转入离线消息
+    offline_gates: Vec<tokio::sync::RwLock<()>>,
+    /// 连接准入控制
+    guard: Arc<SocketGuard>,
+    /// 按账号覆盖的速率限制
+    rate_overrides: HashMap<String, RateLimit>,
+    /// TLS握手，未启用TLS时为 None
+    tls: Option<TlsAcceptor>,
+    /// 停止服务的信号
+    shutdown: watch::Sender<bool>,
+}
+
+/// socket 服务句柄，由 SocketServerBuilder 启动，可以克隆后在多处使用
+#[derive(Clone)]
+pub struct SocketServer {
+    state: Arc<SocketServerState>,
+}
+
+/// 已完成认证并注册到注册表的连接，TCP与WebSocket连接共用
+pub(crate) struct SocketConnection {
+    pub(crate) session: SocketSession,
+    /// 发送队列，由连接的写任务负责消费
+    pub(crate) queue: Arc<OutboundQueue>,
+    /// 断开连接的信号
+    pub(crate) closer: Arc<watch::Sender<bool>>,
+    /// 收发统计
+    pub(crate) stats: Arc<SocketStats>,
+    /// 入站限速器
+    limiter: RateLimiter,
+}
 
 impl SocketServer {
 
-    // 定义一个用于处理客户端连接的函数
-    pub async fn init_socket_server(address:&str) -> std::io::Result<()>{
-        // 接受客户端连接
-        let listener = TcpListener::bind(address).await.unwrap();
-        log::info!(" - Socket Server Local Address:   {}",address.replace("0.0.0.0", "127.0.0.1"));
+    /// 绑定地址并启动接收连接、心跳、重发以及集群监听等后台任务
+    pub(crate) async fn start(builder: SocketServerBuilder) -> Result<SocketServer> {
+        // 证书或私钥有误时不启动服务，避免退化为明文连接
+        let tls = match &builder.tls {
+            Some(tls) => Some(tls.acceptor()?),
+            None => None,
+        };
+        let limits = builder.limits;
+        let guard = SocketGuard::new(limits.max_connections, limits.max_connections_per_ip, &builder.allow, &builder.deny)?;
+        let listener = match TcpListener::bind(&builder.address).await {
+            Ok(listener) => listener,
+            Err(e) => return Err(Error::from(format!("socket服务绑定地址{}失败:{}", builder.address, e))),
+        };
+        let local_addr = listener.local_addr()?;
+        let (shutdown, _) = watch::channel(false);
+        let server = SocketServer {
+            state: Arc::new(SocketServerState {
+                local_addr,
+                registry: builder.registry.unwrap_or_default(),
+                handlers: builder.handlers,
+                codec: builder.codec,
+                limits: builder.limits,
+                authenticator: builder.authenticator.unwrap_or_else(SocketServer::token_authenticator),
+                methods: builder.methods,
+                calls: Mutex::new(HashMap::new()),
+                ids: Mutex::new(builder.cluster.as_ref().map(SocketCluster::id_generator).unwrap_or_else(Snowflake::default)),
+                cluster: builder.cluster,
+                offline: builder.offline,
+                offline_gates: (0..OFFLINE_GATE_STRIPES).map(|_| tokio::sync::RwLock::new(())).collect(),
+                guard: Arc::new(guard),
+                rate_overrides: builder.rate_overrides,
+                tls,
+                shutdown,
+            }),
+        };
+        log::info!(" - Socket Server Local Address:   {}", local_addr);
+        if let Some(tls) = &builder.tls {
+            log::info!(" - Socket Server TLS Certificate: {}，校验客户端证书:{}", tls.cert, tls.client_ca.is_some());
+        }
+        // 连接任务需要访问redis，统一交给tokio运行时调度
+        tokio::spawn(server.clone().accept_loop(listener));
+        // 定时发送心跳并清理空闲连接
+        tokio::spawn(server.clone().reap_idle_clients());
+        // 定时重发未确认的推送消息
+        tokio::spawn(server.clone().redeliver_unacked());
+        // 接收其他节点转发的推送
+        if server.state.cluster.is_some() {
+            tokio::spawn(server.clone().listen_cluster());
+        }
+        Ok(server)
+    }
+
+    /// 默认的握手认证：校验 access_token 对应的redis会话
+    fn token_authenticator() -> SocketAuthenticator {
+        Arc::new(|handshake: SocketHandshakeDTO| -> BoxFuture<'static, Result<UserContext>> {
+            Box::pin(async move {
+                let token = handshake.access_token.unwrap_or_default();
+                if token.is_empty() {
+                    return Err(Error::from(("access_token不能为空", NOT_AUTHORIZE_CODE)));
+                }
+                UserContext::verify(&token).await
+            })
+        })
+    }
+
+    /// 生成推送消息或远程调用的id
+    pub fn message_id(&self) -> String {
+        self.state.ids.lock().unwrap().generate().to_string()
+    }
+
+    /// 构造一条由当前服务分配id的推送消息
+    pub fn message(&self, topic: Option<&str>, payload: Value) -> SocketPushDTO {
+        SocketPushDTO::new(&self.message_id(), topic, payload)
+    }
+
+    /// 服务实际监听的地址（绑定端口0时可以由此获取分配的端口）
+    pub fn local_addr(&self) -> SocketAddr {
+        self.state.local_addr
+    }
+
+    /// 服务使用的连接注册表
+    pub fn registry(&self) -> Arc<RwLock<SocketRegistry>> {
+        self.state.registry.clone()
+    }
+
+    pub(crate) fn handlers(&self) -> &[Arc<dyn MessageHandler>] {
+        &self.state.handlers
+    }
+
+    /// 查找客户端可以远程调用的方法
+    pub(crate) fn method(&self, name: &str) -> Option<SocketMethod> {
+        self.state.methods.get(name).cloned()
+    }
+
+    pub(crate) fn codec(&self) -> SocketCodec {
+        self.state.codec
+    }
+
+    pub(crate) fn limits(&self) -> SocketLimits {
+        self.state.limits
+    }
+
+    /// 检查是否允许来自该IP的新连接，允许时返回连接占用的名额，连接结束时释放
+    pub(crate) fn admit(&self, ip: IpAddr) -> Result<ConnectionPermit> {
+        self.state.guard.admit(ip)
+    }
+
+    /// 服务是否已经开始停止
+    pub fn is_shutdown(&self) -> bool {
+        *self.state.shutdown.borrow()
+    }
+
+    /// 停止服务：不再接收新连接并停止后台任务，向全部连接发送 server_closing 消息，
+    /// 在 drain_timeout 内等待发送队列中的消息写完，超时后强制断开剩余的连接
+    pub async fn shutdown(&self, drain_timeout: Duration) {
+        self.state.shutdown.send_replace(true);
+        {
+            let closing = SocketMessageDTO::new(SocketMessageType::ServerClosing, Value::Null).to_string();
+            let registry = self.state.registry.read().unwrap();
+            for client in registry.values() {
+                client.send(closing.clone());
+                // 写任务发送完队列中的消息后断开连接，连接的读任务随之结束并从注册表中移除
+                client.queue.close();
+            }
+        }
+        let deadline = Instant::now() + drain_timeout;
+        while !self.state.registry.read().unwrap().is_empty() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let clients: Vec<SocketClientInfo> = {
+            let mut registry = self.state.registry.write().unwrap();
+            let ids: Vec<u64> = registry.values().map(|client| client.id).collect();
+            ids.iter().filter_map(|id| registry.remove(id)).collect()
+        };
+        log::info!("socket服务 {} 已停止，强制断开未写完的连接{}个", self.state.local_addr, clients.len());
+        for client in clients {
+            self.release(client).await;
+        }
+    }
+
+    /// 循环接收客户端连接，直到服务停止
+    /// 接受连接失败（如文件描述符耗尽）时按 ACCEPT_BACKOFF_MIN 起翻倍退避，最长 ACCEPT_BACKOFF_MAX
+    async fn accept_loop(self, listener: TcpListener) {
+        let mut shutdown = self.state.shutdown.subscribe();
+        let mut backoff = ACCEPT_BACKOFF_MIN;
         loop {
-            // 接受客户端连接
-            let (mut stream, _) = listener.accept().await.unwrap();
-            let mut reader = stream.clone();
-            // 生成一个唯一的客户端 ID
-            let id = rand::random::<u64>();
-            // 使用 Arc<RwLock<HashMap<u64, ClientInfo>>> 来获取 CLIENTS
-            let clients_rw = SOCKET_CLIENTS.clone();
-
-            // 使用 RwLock::write().await 来获取 CLIENTS 的写锁
-            let mut clients = clients_rw.write().unwrap();
-
-            // 将客户端信息加入到哈希表中
-            clients.insert(id, SocketClientInfo {
-                stream,
-                lock: RwLock::new(HashMap::new()),
-            });
-            // 处理客户端连接
-            async_std::task::spawn(async move {
-                log::info!("Accepted from: {}", reader.peer_addr().unwrap());
-                // 循环接收客户端消息
-                loop {
-                    // 读取客户端消息
-                    let mut buf = [0u8; 1024];
-                    let read_size = reader.read(&mut buf).await;
-                    if read_size.is_err() {
-                        log::error!("数据接收异常，可能是客户端主动断开连接");
-                        break;
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                accepted = listener.accept() => match accepted {
+                    // 处理客户端连接
+                    Ok((stream, peer_addr)) => {
+                        backoff = ACCEPT_BACKOFF_MIN;
+                        match self.admit(peer_addr.ip()) {
+                            Ok(permit) => {
+                                tokio::spawn(self.clone().accept(stream, peer_addr, permit));
+                            }
+                            Err(e) => log::warn!("拒绝客户端 {} 的连接:{}", peer_addr, e),
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("socket服务接受连接失败:{}，{}毫秒后重试", e, backoff.as_millis());
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
                     }
-                    let size = read_size.unwrap();
-                    if size == 0 {
-                        log::error!("接收到客户端发来的空数据");
+                },
+            }
+        }
+    }
+
+    /// 启用TLS时先完成TLS握手（超时时间与认证超时相同），再处理连接；permit 在连接结束时释放
+    async fn accept(self, stream: TcpStream, peer_addr: SocketAddr, _permit: ConnectionPermit) {
+        let acceptor = match &self.state.tls {
+            Some(acceptor) => acceptor.clone(),
+            None => return self.handle_connection(stream, peer_addr).await,
+        };
+        let auth_timeout = self.state.limits.auth_timeout;
+        match tokio::time::timeout(auth_timeout, acceptor.accept(stream)).await {
+            Ok(Ok(stream)) => self.handle_connection(stream, peer_addr).await,
+            Ok(Err(e)) => log::error!("客户端 {} TLS握手失败:{}", peer_addr, e),
+            Err(_) => log::error!("客户端 {} 在{}秒内未完成TLS握手", peer_addr, auth_timeout.as_secs()),
+        }
+    }
+
+    /// 处理单个客户端连接：先完成握手认证，再注册到注册表并循环接收消息，明文与TLS连接使用相同的处理逻辑
+    async fn handle_connection<S>(self, mut stream: S, peer_addr: SocketAddr)
+        where
+            S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        log::info!("Accepted from: {}", peer_addr);
+        let codec = self.state.codec;
+        let limits = self.state.limits;
+        // 尚未组成完整帧的数据
+        let mut buffer: Vec<u8> = Vec::new();
+        // 第一帧必须携带access_token，超时未认证的连接直接关闭
+        let handshake = tokio::time::timeout(
+            limits.auth_timeout,
+            self.authenticate(&mut stream, &mut buffer),
+        ).await;
+        let (user, handshake, auth_id) = match handshake {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => {
+                log::error!("客户端 {} 认证失败:{}", peer_addr, e);
+                SocketServer::reject(stream, &codec, &e).await;
+                return;
+            }
+            Err(_) => {
+                log::error!("客户端 {} 在{}秒内未完成认证", peer_addr, limits.auth_timeout.as_secs());
+                let e = Error::from(("认证超时", NOT_AUTHORIZE_CODE));
+                SocketServer::reject(stream, &codec, &e).await;
+                return;
+            }
+        };
+        let mut connection = self.open(user, handshake, auth_id, &peer_addr.to_string()).await;
+        let id = connection.session.id;
+        // 读写分别由不同的任务负责，连接的断开通过 closer 通知两个任务
+        let (mut reader, writer) = tokio::io::split(stream);
+        tokio::spawn(SocketServer::write_loop(id, writer, codec, connection.queue.clone(), connection.closer.clone(), connection.stats.clone()));
+        let closed = connection.closer.subscribe();
+        // 循环接收客户端消息
+        loop {
+            let frame: String = tokio::select! {
+                // 连接被服务端断开（踢下线、队列溢出、写入失败等）
+                _ = SocketServer::closed(closed.clone()) => break,
+                read = SocketServer::read_frame(&mut reader, &codec, &mut buffer) => match read {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::error!("客户端 {} 数据接收异常:{}", id, e);
                         break;
                     }
-                    let _message:String = String::from_utf8_lossy(&buf[..size]).to_string();
-                    if _message.starts_with("address") {
-                        // 接收到客户端是身份地址信息
-                        println!("接收到客户端是身份地址信息");
-                    }else{
-                        // 打印客户端消息
-                        println!("客户端 {} 发送消息: {}", id, _message);
+                },
+            };
+            if !self.receive(&mut connection, &frame).await {
+                break;
+            }
+        }
+        self.disconnect(connection).await;
+    }
+
+    /// 把已认证的连接注册到注册表并绑定账号，回复认证结果、补发离线消息并通知消息处理器；
+    /// 返回的发送队列由调用方按各自的传输方式（TCP、WebSocket）写给客户端
+    pub(crate) async fn open(&self, user: UserContext, handshake: SocketHandshakeDTO, auth_id: Option<String>, peer_addr: &str) -> SocketConnection {
+        let limits = self.state.limits;
+        let device = handshake.device.filter(|item| !item.is_empty());
+        let (closer, _) = watch::channel(false);
+        let closer = Arc::new(closer);
+        let queue = Arc::new(OutboundQueue::new(limits.queue_capacity, limits.overflow_policy));
+        let stats = Arc::new(SocketStats::default());
+        let id = self.state.registry.write().unwrap().next_id();
+        let now = DateUtils::now();
+        let client = SocketClientInfo {
+            id,
+            closer: closer.clone(),
+            queue: queue.clone(),
+            account: None,
+            device: None,
+            organize: Some(user.organize),
+            tags: handshake.tags.unwrap_or_default().into_iter().collect(),
+            topics: HashSet::new(),
+            peer_addr: peer_addr.to_string(),
+            connect_time: now,
+            last_active: AtomicI64::new(now.timestamp_millis()),
+            sequence: AtomicU64::new(0),
+            pending_acks: Mutex::new(HashMap::new()),
+            pending_calls: Mutex::new(HashMap::new()),
+            stats: stats.clone(),
+        };
+        queue.push(SocketMessageDTO::reply(auth_id, &Ok(user.account.clone())).to_string());
+        let gate = if self.state.offline {
+            Some(self.offline_gate(&user.account).write().await)
+        } else {
+            None
+        };
+        // 同一账号同一设备的旧连接先收到kick后断开，其未确认的消息转入离线消息，随后与其他离线消息一起补发给新连接
+        let previous = self.state.registry.read().unwrap().find_by_device(&user.account, device.as_deref().unwrap_or(DEFAULT_DEVICE));
+        if let Some(old) = previous {
+            log::info!("账号:{} 设备:{} 在新连接上登录，断开旧连接 {}", user.account, device.clone().unwrap_or_default(), old);
+            self.kick_clients(&[old], REPLACED_REASON).await;
+        }
+        // 补发账号离线期间的消息：在连接对注册表可见之前放入发送队列，保证离线消息排在之后的实时推送之前
+        if gate.is_some() {
+            match self.deliver_offline(&user.account, &client).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("向客户端 {} 补发离线消息{}条", id, count),
+                Err(e) => log::error!("向客户端 {} 补发离线消息失败:{}", id, e),
+            }
+        }
+        // 使用 RwLock::write() 来获取注册表的写锁，注册连接并绑定账号
+        let replaced = {
+            let mut clients = self.state.registry.write().unwrap();
+            clients.insert(client);
+            clients.bind(id, &user.account, device.as_deref())
+        };
+        drop(gate);
+        log::info!("客户端 {} 认证成功，连接id:{}，账号:{}，设备:{}", peer_addr, id, user.account, device.clone().unwrap_or_default());
+        // 同一设备几乎同时登录时，先完成绑定的连接在这里被替换
+        if let Some(old) = replaced {
+            log::info!("账号:{} 设备:{} 的旧连接 {} 已被替换", user.account, device.clone().unwrap_or_default(), old);
+            self.kick_clients(&[old], REPLACED_REASON).await;
+        }
+        if let Some(cluster) = &self.state.cluster {
+            // 登记账号在当前节点上线，并断开该账号同一设备在其他节点上的旧连接
+            if let Err(e) = cluster.join(&user.account).await {
+                log::error!("登记账号:{} 的在线节点失败:{}", user.account, e);
+            }
+            let kick = ClusterEvent::Kick {
+                account: user.account.clone(),
+                device: Some(device.clone().unwrap_or_else(|| DEFAULT_DEVICE.to_string())),
+                reason: "账号在其他节点上登录".to_string(),
+            };
+            if let Err(e) = cluster.relay(&user.account, &kick).await {
+                log::error!("通知其他节点断开账号:{} 的旧连接失败:{}", user.account, e);
+            }
+        }
+        let session = SocketSession::new(id, &user.account, device.as_deref(), peer_addr, queue.clone(), stats.clone(), closer.clone());
+        SocketDispatcher::connect(self, &session);
+        let limit = self.state.rate_overrides.get(&user.account).copied().unwrap_or(limits.rate_limit);
+        let limiter = RateLimiter::new(limit, limits.rate_warn_after, limits.rate_disconnect_after);
+        SocketConnection { session, queue, closer, stats, limiter }
+    }
+
+    /// 处理已认证的连接收到的一帧：超过速率限制时先限速，连续超限时警告，仍不降速则断开；
+    /// 返回 false 表示连接需要断开
+    pub(crate) async fn receive(&self, connection: &mut SocketConnection, frame: &str) -> bool {
+        let session = &connection.session;
+        connection.stats.received(frame.len());
+        let decision = connection.limiter.check(frame.len());
+        if decision != RateDecision::Allow {
+            connection.stats.throttled.fetch_add(1, Ordering::Relaxed);
+        }
+        match decision {
+            RateDecision::Allow => {}
+            // 暂停读取，由TCP背压限制客户端，不记录日志以免日志被刷屏
+            RateDecision::Throttle(wait) => tokio::time::sleep(wait).await,
+            RateDecision::Warn(wait) => {
+                log::warn!("客户端 {}（账号:{}，地址:{}）发送消息过快，已限速", session.id, session.account, session.peer_addr);
+                let e = Error::from(("发送消息过快，请降低发送频率，否则连接将被断开", BAD_REQUEST_ERROR_CODE));
+                session.send(&SocketMessageDTO::error(None, &e));
+                tokio::time::sleep(wait).await;
+            }
+            RateDecision::Disconnect => {
+                log::warn!("客户端 {}（账号:{}，地址:{}）持续超过速率限制，断开连接", session.id, session.account, session.peer_addr);
+                let e = Error::from(("发送消息过快，连接已断开", BAD_REQUEST_ERROR_CODE));
+                session.send(&SocketMessageDTO::error(None, &e));
+                // 等待写任务发送完错误消息
+                session.close();
+                let _ = tokio::time::timeout(Duration::from_secs(1), SocketServer::closed(connection.closer.subscribe())).await;
+                return false;
+            }
+        }
+        self.touch(session.id);
+        SocketDispatcher::dispatch(self, session, frame).await;
+        true
+    }
+
+    /// 刷新连接的最后活跃时间
+    pub(crate) fn touch(&self, id: u64) {
+        self.state.registry.read().unwrap().touch(&id);
+    }
+
+    /// 连接的读任务结束后调用：通知消息处理器，并从注册表中移除连接
+    pub(crate) async fn disconnect(&self, connection: SocketConnection) {
+        let id = connection.session.id;
+        log::info!("客户端 {} 断开连接", id);
+        SocketDispatcher::disconnect(self, &connection.session);
+        // 从注册表中删除客户端连接信息
+        let client = self.state.registry.write().unwrap().remove(&id);
+        // 断开客户端连接
+        match client {
+            Some(client) => self.release(client).await,
+            None => {
+                connection.closer.send_replace(true);
+            }
+        }
+    }
+
+    /// 取出账号的离线消息并推送给指定连接，返回补发的消息数
+    async fn deliver_offline(&self, account: &str, client: &SocketClientInfo) -> Result<u64> {
+        let messages = CONTEXT.offline_message_service.take(account).await?;
+        Ok(messages.iter().filter(|message| client.push(message, None).is_queued()).count() as u64)
+    }
+
+    /// 账号对应的离线消息补发锁
+    fn offline_gate(&self, account: &str) -> &tokio::sync::RwLock<()> {
+        let mut hasher = DefaultHasher::new();
+        account.hash(&mut hasher);
+        &self.state.offline_gates[hasher.finish() as usize % OFFLINE_GATE_STRIPES]
+    }
+
+    /// 断开已从注册表移除的连接，未确认的推送消息转入离线消息，待账号重新连接后补发
+    async fn release(&self, client: SocketClientInfo) {
+        client.close();
+        let account = match &client.account {
+            Some(account) => account.clone(),
+            None => return,
+        };
+        // 账号在当前节点已没有连接时，登记下线
+        let online = !self.state.registry.read().unwrap().find_by_account(&account).is_empty();
+        if let (Some(cluster), false) = (&self.state.cluster, online) {
+            if let Err(e) = cluster.leave(&account).await {
+                log::error!("登记账号:{} 下线失败:{}", account, e);
+            }
+        }
+        // 待确认列表中只有发给账号的消息，广播、组播与主题消息只发给当时在线的连接，不做补发
+        let unacked = client.take_unacked();
+        if unacked.is_empty() {
+            return;
+        }
+        if !self.state.offline {
+            log::warn!("客户端 {} 断开时有{}条消息未确认，未启用离线消息，消息被丢弃", client.id, unacked.len());
+            return;
+        }
+        log::info!("客户端 {} 断开时有{}条消息未确认，转入账号:{} 的离线消息", client.id, unacked.len(), account);
+        for message in unacked {
+            if let Err(e) = CONTEXT.offline_message_service.store(&account, &message).await {
+                log::error!("保存账号:{} 的离线消息失败:{}", account, e);
+            }
+        }
+    }
+
+    /// 监听其他节点转发的事件，订阅连接断开后间隔一秒重新监听
+    async fn listen_cluster(self) {
+        let cluster = match &self.state.cluster {
+            Some(cluster) => cluster,
+            None => return,
+        };
+        let mut shutdown = self.state.shutdown.subscribe();
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                result = cluster.listen(|event| self.clone().on_cluster_event(event)) => {
+                    if let Err(e) = result {
+                        log::error!("socket集群监听异常:{}", e);
+                    }
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    /// 处理其他节点转发的事件，只投递给当前节点上的连接
+    async fn on_cluster_event(self, event: ClusterEvent) {
+        let result = match event {
+            ClusterEvent::Push { account, message } => match self.send(&account, &message) {
+                // 账号已从当前节点断开，登记下线并转入离线消息
+                Ok(0) => {
+                    if let Some(cluster) = &self.state.cluster {
+                        let _ = cluster.leave(&account).await;
+                    }
+                    if self.state.offline {
+                        CONTEXT.offline_message_service.store(&account, &message).await.map(|_| ())
+                    } else {
+                        Ok(())
+                    }
+                }
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            },
+            ClusterEvent::Broadcast { message } => self.send_matching(&message, |_| true).map(|_| ()),
+            ClusterEvent::Multicast { filter, message } => {
+                self.send_matching(&message, |client| filter.matches(client)).map(|_| ())
+            }
+            ClusterEvent::Publish { topic, message } => self.publish(&topic, &message).map(|_| ()),
+            ClusterEvent::Kick { account, device, reason } => {
+                self.kick(&account, device.as_deref(), &reason).await;
+                Ok(())
+            }
+            ClusterEvent::Call { node, call_id, account, device, method, params, timeout_ms } => {
+                // 账号在当前节点上没有可调用的连接时不应答，由持有连接的节点应答
+                let target = self.state.registry.read().unwrap().find_call_target(&account, device.as_deref());
+                if let Some(id) = target {
+                    // 等待客户端应答可能持续到超时，不能阻塞集群事件的处理
+                    tokio::spawn(async move {
+                        let result = self.call_client(id, &method, params, Duration::from_millis(timeout_ms)).await;
+                        let event = ClusterEvent::CallResult {
+                            response: SocketMessageDTO::response(Some(call_id.clone()), &result),
+                            call_id,
+                        };
+                        if let Some(cluster) = &self.state.cluster {
+                            if let Err(e) = cluster.send_to(&node, &event).await {
+                                log::error!("向socket节点:{} 返回远程调用结果失败:{}", node, e);
+                            }
+                        }
+                    });
+                }
+                Ok(())
+            }
+            ClusterEvent::CallResult { call_id, response } => {
+                // 同一账号在多个节点上有连接时，以最先返回的结果为准
+                if let Some(sender) = self.state.calls.lock().unwrap().remove(&call_id) {
+                    let _ = sender.send(response.response_result());
+                }
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            log::error!("处理socket集群转发的事件失败:{}", e);
+        }
+    }
+
+    /// 断开当前服务上账号的连接，device 为空时断开该账号的全部设备，返回断开的连接数
+    pub async fn kick(&self, account: &str, device: Option<&str>, reason: &str) -> u64 {
+        let ids = {
+            let clients = self.state.registry.read().unwrap();
+            match device {
+                Some(device) => clients.find_by_device(account, device).into_iter().collect(),
+                None => clients.find_by_account(account),
+            }
+        };
+        self.kick_clients(&ids, reason).await
+    }
+
+    /// 按连接id断开当前服务上的连接，返回连接是否存在
+    pub async fn kick_client(&self, id: u64, reason: &str) -> bool {
+        self.kick_clients(&[id], reason).await > 0
+    }
+
+    /// 向连接发送 kick 消息告知原因，等待写任务发送完后断开，返回断开的连接数
+    async fn kick_clients(&self, ids: &[u64], reason: &str) -> u64 {
+        let kicked: Vec<SocketClientInfo> = {
+            let mut clients = self.state.registry.write().unwrap();
+            ids.iter().filter_map(|id| clients.remove(id)).collect()
+        };
+        let frame = SocketMessageDTO::new(SocketMessageType::Kick, Value::String(reason.to_string())).to_string();
+        for client in &kicked {
+            log::info!("客户端 {}（账号:{}）被断开:{}", client.id, client.account.clone().unwrap_or_default(), reason);
+            client.send(frame.clone());
+            client.queue.close();
+        }
+        let flushed = kicked
+            .iter()
+            .map(|client| tokio::time::timeout(KICK_FLUSH_TIMEOUT, SocketServer::closed(client.closer.subscribe())));
+        futures::future::join_all(flushed).await;
+        let count = kicked.len() as u64;
+        for client in kicked {
+            self.release(client).await;
+        }
+        count
+    }
+
+    /// 调用账号连接上的方法并等待客户端应答：指定设备时调用该设备的连接，否则调用账号最近建立的连接；
+    /// 当前节点上没有该账号的连接时经集群转发给持有连接的节点，超过 timeout 未应答时返回错误
+    pub async fn call(&self, account: &str, device: Option<&str>, method: &str, params: Value, timeout: Duration) -> Result<Value> {
+        let target = self.state.registry.read().unwrap().find_call_target(account, device);
+        if let Some(id) = target {
+            return self.call_client(id, method, params, timeout).await;
+        }
+        let offline = Error::from((format!("账号:{} 不在线", account), NOT_EXIST_CODE));
+        let cluster = match &self.state.cluster {
+            Some(cluster) => cluster,
+            None => return Err(offline),
+        };
+        let call_id = self.message_id();
+        let (sender, receiver) = oneshot::channel();
+        self.state.calls.lock().unwrap().insert(call_id.clone(), sender);
+        let event = ClusterEvent::Call {
+            node: cluster.node_id.clone(),
+            call_id: call_id.clone(),
+            account: account.to_string(),
+            device: device.map(|item| item.to_string()),
+            method: method.to_string(),
+            params,
+            timeout_ms: timeout.as_millis() as u64,
+        };
+        let nodes = cluster.relay(account, &event).await;
+        let result = match nodes {
+            Ok(0) => Err(offline),
+            Ok(_) => match tokio::time::timeout(timeout, receiver).await {
+                Ok(Ok(result)) => result,
+                Ok(Err(_)) => Err(Error::from(format!("账号:{} 的远程调用{}已取消", account, method))),
+                Err(_) => Err(Error::from(format!("账号:{} 在{}毫秒内未应答调用{}", account, timeout.as_millis(), method))),
+            },
+            Err(e) => Err(e),
+        };
+        self.state.calls.lock().unwrap().remove(&call_id);
+        result
+    }
+
+    /// 调用当前节点上指定连接的方法并等待客户端应答，超过 timeout 未应答时返回错误
+    pub async fn call_client(&self, id: u64, method: &str, params: Value, timeout: Duration) -> Result<Value> {
+        let call_id = self.message_id();
+        let receiver = {
+            let clients = self.state.registry.read().unwrap();
+            match clients.get(&id) {
+                Some(client) => client.call(&call_id, method, params)?,
+                None => return Err(Error::from((format!("连接 {} 不存在", id), NOT_EXIST_CODE))),
+            }
+        };
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(result)) => result,
+            // 连接断开时待应答的调用随连接一起丢弃
+            Ok(Err(_)) => Err(Error::from(format!("连接 {} 在应答调用{}前已断开", id, method))),
+            Err(_) => {
+                if let Some(client) = self.state.registry.read().unwrap().get(&id) {
+                    client.cancel_call(&call_id);
+                }
+                Err(Error::from(format!("连接 {} 在{}毫秒内未应答调用{}", id, timeout.as_millis(), method)))
+            }
+        }
+    }
+
+    /// 重发超时未确认的推送消息，超过重发上限的消息转入死信
+    async fn redeliver_unacked(self) {
+        let timeout = self.state.limits.ack_timeout;
+        let max_retry = self.state.limits.ack_max_retry;
+        let mut shutdown = self.state.shutdown.subscribe();
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+            }
+            let mut dead_letters = vec![];
+            {
+                let clients = self.state.registry.read().unwrap();
+                for client in clients.values() {
+                    for message in client.retry_unacked(timeout, max_retry) {
+                        dead_letters.push((client.id, client.account.clone().unwrap_or_default(), message));
+                    }
+                }
+            }
+            for (id, account, message) in dead_letters {
+                log::warn!("客户端 {} 的消息:{} 重发{}次后仍未确认，转入死信", id, message.id, max_retry);
+                if !self.state.offline {
+                    continue;
+                }
+                if let Err(e) = CONTEXT.offline_message_service.store_dead_letter(&account, &message).await {
+                    log::error!("保存账号:{} 的死信消息失败:{}", account, e);
+                }
+            }
+        }
+    }
+
+    /// 心跳及空闲连接清理任务：空闲超过心跳间隔的连接发送ping，超过空闲超时的连接直接断开
+    async fn reap_idle_clients(self) {
+        let interval = self.state.limits.heartbeat_interval.max(Duration::from_secs(1));
+        let idle_timeout = self.state.limits.effective_idle_timeout().map(|timeout| timeout.as_secs() as i64);
+        let mut shutdown = self.state.shutdown.subscribe();
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                _ = tokio::time::sleep(interval) => {}
+            }
+            let now = DateUtils::now();
+            let mut expired = vec![];
+            {
+                let clients = self.state.registry.read().unwrap();
+                for client in clients.values() {
+                    let idle = client.idle_seconds(&now);
+                    if idle_timeout.is_some_and(|timeout| idle >= timeout) {
+                        expired.push((client.id, idle));
+                    } else if idle >= interval.as_secs() as i64 {
+                        client.send(SocketMessageDTO::new(SocketMessageType::Ping, Value::Null).to_string());
+                    }
+                }
+            }
+            if expired.is_empty() {
+                continue;
+            }
+            let mut evicted = vec![];
+            {
+                let mut clients = self.state.registry.write().unwrap();
+                for (id, idle) in expired {
+                    if let Some(client) = clients.remove(&id) {
+                        log::warn!(
+                            "客户端 {}（账号:{}，地址:{}）已{}秒未收到任何数据，超过空闲超时{}秒，断开连接",
+                            id,
+                            client.account.clone().unwrap_or_default(),
+                            client.peer_addr,
+                            idle,
+                            idle_timeout.unwrap_or_default()
+                        );
+                        evicted.push(client);
                     }
                 }
-                log::info!("客户端断开连接");
-                // 从哈希表中删除客户端连接信息
-                let _clients_rw = SOCKET_CLIENTS.clone();
-                // 使用 RwLock::write().await 来获取 CLIENTS 的写锁
-                let mut _clients = _clients_rw.write().unwrap();
-                _clients.remove(&id);
-                // 断开客户端连接
-                let _ = reader.shutdown(Shutdown::Both).is_ok();
+            }
+            for client in evicted {
+                self.release(client).await;
+            }
+        }
+    }
+
+    /// 读取一个完整的帧，连接关闭时返回 None
+    async fn read_frame<R>(reader: &mut R, codec: &SocketCodec, buffer: &mut Vec<u8>) -> Result<Option<String>>
+        where
+            R: AsyncRead + Unpin,
+    {
+        loop {
+            // 一次读取可能包含多个帧，也可能不足一帧
+            if let Some(frame) = codec.decode(buffer)? {
+                return Ok(Some(frame));
+            }
+            let mut buf = [0u8; 4096];
+            let size = reader.read(&mut buf).await?;
+            if size == 0 {
+                return Ok(None);
+            }
+            buffer.extend_from_slice(&buf[..size]);
+        }
+    }
+
+    /// 读取 auth 类型的握手帧并完成认证，返回登录用户、握手信息以及握手消息的id
+    async fn authenticate<R>(&self, reader: &mut R, buffer: &mut Vec<u8>) -> Result<(UserContext, SocketHandshakeDTO, Option<String>)>
+        where
+            R: AsyncRead + Unpin,
+    {
+        let frame = match SocketServer::read_frame(reader, &self.state.codec, buffer).await? {
+            Some(frame) => frame,
+            None => return Err(Error::from("客户端在认证前断开连接")),
+        };
+        self.login(&frame).await
+    }
+
+    /// 解析 auth 类型的握手消息并完成认证，返回登录用户、握手信息以及握手消息的id
+    pub(crate) async fn login(&self, frame: &str) -> Result<(UserContext, SocketHandshakeDTO, Option<String>)> {
+        let message = SocketMessageDTO::parse(frame)?;
+        if message.message_type != SocketMessageType::Auth {
+            return Err(Error::from(("连接建立后的第一条消息必须是auth", NOT_AUTHORIZE_CODE)));
+        }
+        let handshake: SocketHandshakeDTO = message.payload_as()?;
+        let user = (self.state.authenticator)(handshake.clone()).await?;
+        Ok((user, handshake, message.id))
+    }
+
+    /// 向未通过认证的客户端回复错误帧并断开连接
+    async fn reject<W>(mut stream: W, codec: &SocketCodec, error: &Error)
+        where
+            W: AsyncWrite + Unpin,
+    {
+        if let Ok(frame) = codec.encode(&SocketMessageDTO::error(None, error).to_string()) {
+            let _ = stream.write_all(&frame).await;
+        }
+        let _ = stream.shutdown().await;
+    }
+
+    /// 连接的写任务，按顺序把发送队列中的消息写入客户端
+    /// 发送队列关闭且消息写完后，或收到断开信号时结束，并通知读任务断开连接
+    async fn write_loop<W>(id: u64, mut stream: W, codec: SocketCodec, queue: Arc<OutboundQueue>, closer: Arc<watch::Sender<bool>>, stats: Arc<SocketStats>)
+        where
+            W: AsyncWrite + Unpin,
+    {
+        let closed = closer.subscribe();
+        loop {
+            let message = tokio::select! {
+                _ = SocketServer::closed(closed.clone()) => break,
+                message = queue.pop() => match message {
+                    Some(message) => message,
+                    None => break,
+                },
+            };
+            let frame = match codec.encode(&message) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    log::error!("向客户端 {} 发送的消息无法编码:{}", id, e);
+                    continue;
+                }
+            };
+            if let Err(e) = stream.write_all(&frame).await {
+                log::error!("向客户端 {} 发送消息失败:{}", id, e);
+                break;
+            }
+            stats.sent(message.len());
+        }
+        queue.close();
+        let _ = stream.shutdown().await;
+        closer.send_replace(true);
+    }
+
+    /// 等待连接的断开信号
+    pub(crate) async fn closed(mut closed: watch::Receiver<bool>) {
+        while !*closed.borrow_and_update() {
+            if closed.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// 向账号的所有在线连接推送一条消息，返回成功进入发送队列的连接数
+    pub fn send(&self, account: &str, message: &SocketPushDTO) -> Result<u64> {
+        self.validate(message)?;
+        // 入队不涉及网络读写，持有读锁的时间很短
+        Ok(self.state.registry.read().unwrap().send_to_account(account, message, false).0)
+    }
+
+    /// 向账号的所有在线连接推送一条消息，并返回每个连接确认结果的接收端，列表为空表示账号不在线
+    pub fn send_with_ack(&self, account: &str, message: &SocketPushDTO) -> Result<Vec<oneshot::Receiver<bool>>> {
+        self.validate(message)?;
+        Ok(self.state.registry.read().unwrap().send_to_account(account, message, true).1)
+    }
+
+    /// 向所有满足条件的连接推送一条消息，返回（匹配的连接数，成功进入发送队列的连接数）
+    pub fn send_matching<F>(&self, message: &SocketPushDTO, filter: F) -> Result<(u64, u64)>
+        where
+            F: Fn(&SocketClientInfo) -> bool,
+    {
+        self.validate(message)?;
+        Ok(self.state.registry.read().unwrap().send_matching(message, filter))
+    }
+
+    /// 向订阅了主题的所有连接推送一条消息，返回（订阅的连接数，成功进入发送队列的连接数）
+    pub fn publish(&self, topic: &str, message: &SocketPushDTO) -> Result<(u64, u64)> {
+        self.validate(message)?;
+        Ok(self.state.registry.read().unwrap().publish(topic, message))
+    }
+
+    /// 把事件转发给账号在线的其他节点，返回收到事件的节点数；
+    /// 未启用集群时返回0，转发失败（如redis不可用）时记录日志并返回0，不影响当前节点上已完成的投递
+    pub async fn relay(&self, account: &str, event: &ClusterEvent) -> u64 {
+        let cluster = match &self.state.cluster {
+            Some(cluster) => cluster,
+            None => return 0,
+        };
+        match cluster.relay(account, event).await {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                log::error!("向账号:{} 在线的其他socket节点转发事件失败:{}", account, e);
+                0
+            }
+        }
+    }
+
+    /// 把事件广播给集群中的其他节点，返回收到事件的节点数；未启用集群或转发失败时返回0
+    pub async fn relay_all(&self, event: &ClusterEvent) -> u64 {
+        let cluster = match &self.state.cluster {
+            Some(cluster) => cluster,
+            None => return 0,
+        };
+        match cluster.broadcast(event).await {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                log::error!("向其他socket节点广播事件失败:{}", e);
+                0
+            }
+        }
+    }
+
+    /// 投递一条推送消息给账号：账号在其他节点上的连接由集群转发，完全不在线时转入离线消息；
+    /// wait_ack 为等待客户端确认的秒数，转发到其他节点的消息不等待确认
+    pub async fn deliver(&self, account: &str, message: SocketPushDTO, wait_ack: Option<u64>) -> Result<SocketDeliveryVO> {
+        // 账号正在补发离线消息时等待其完成绑定，之后的消息直接推送给新连接
+        let gate = if self.state.offline {
+            Some(self.offline_gate(account).read().await)
+        } else {
+            None
+        };
+        let receivers = self.send_with_ack(account, &message)?;
+        let connections = receivers.len() as u64;
+        // 账号可能同时在其他节点上有连接
+        let nodes = self.relay(account, &ClusterEvent::Push { account: account.to_string(), message: message.clone() }).await;
+        if connections == 0 && nodes == 0 {
+            if !self.state.offline {
+                return Err(Error::from(format!("账号:{} 不在线", account)));
+            }
+            // 账号不在线，转入离线消息，待账号重新连接后补发
+            CONTEXT.offline_message_service.store(account, &message).await?;
+            return Ok(SocketDeliveryVO {
+                message_id: message.id,
+                account: account.to_string(),
+                state: DeliveryState::Offline,
+                connections,
+                nodes,
             });
         }
+        drop(gate);
+        let mut state = DeliveryState::Queued;
+        if let Some(seconds) = wait_ack.filter(|seconds| *seconds > 0 && connections > 0) {
+            // 任意一个连接确认即视为送达
+            let acks = receivers.into_iter().map(|receiver| {
+                Box::pin(async move {
+                    match receiver.await {
+                        Ok(true) => Ok(()),
+                        _ => Err(()),
+                    }
+                })
+            });
+            if let Ok(Ok(_)) = tokio::time::timeout(Duration::from_secs(seconds), select_ok(acks)).await {
+                state = DeliveryState::Delivered;
+            }
+        }
+        Ok(SocketDeliveryVO {
+            message_id: message.id,
+            account: account.to_string(),
+            state,
+            connections,
+            nodes,
+        })
     }
 
-    pub async fn close() {
-        let id = 1;
-        // 使用 Arc<RwLock<HashMap<u64, ClientInfo>>> 来获取 CLIENTS
-        let clients = SOCKET_CLIENTS.clone();
+    /// 校验推送消息编码后是否超出单帧上限
+    fn validate(&self, message: &SocketPushDTO) -> Result<()> {
+        self.state.codec.validate(&SocketMessageDTO::push(message).to_string())
+    }
 
-        // 使用 RwLock::write().await 来获取 CLIENTS 的写锁
-        let mut clients = clients.write().unwrap();
-        let mut reader = clients.get(&id).unwrap();
-        let _ = reader.stream.shutdown(Shutdown::Both).is_ok();
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use crate::config::redis_client::RedisClient;
+    use crate::config::socket_builder::{SocketLimits, SocketServerBuilder};
+    use crate::config::socket_cluster::{ClusterEvent, SocketCluster};
+    use crate::config::socket_codec::{FrameMode, SocketCodec};
+    use crate::config::socket_handler::{MessageHandler, SocketSession};
+    use crate::config::socket_server::SocketServer;
+    use crate::config::user_context::UserContext;
+    use crate::config::CONTEXT;
+    use crate::domain::dto::socket_message::{SocketMessageDTO, SocketMessageType};
+    use crate::domain::dto::socket_multicast::SocketMulticastDTO;
+    use crate::domain::vo::socket::DeliveryState;
+    use crate::util::constant::{NOT_AUTHORIZE_CODE, NOT_EXIST_CODE};
+    use crate::util::error::Error;
+
+    const REDIS_URL: &str = "redis://127.0.0.1:6379/";
+
+    async fn start() -> SocketServer {
+        builder().start().await.unwrap()
+    }
+
+    /// 以 access_token 作为账号的测试服务，不依赖redis，access_token 为空时认证失败，注册了原样返回参数的 echo 方法
+    fn builder() -> SocketServerBuilder {
+        SocketServerBuilder::new("127.0.0.1:0")
+            .authenticator(|handshake| async move {
+                let account = handshake.access_token.unwrap_or_default();
+                if account.is_empty() {
+                    return Err(Error::from(("access_token不能为空", NOT_AUTHORIZE_CODE)));
+                }
+                Ok(UserContext {
+                    account,
+                    name: String::new(),
+                    organize: 0,
+                    ip: String::new(),
+                    city: String::new(),
+                    leeway: 0,
+                })
+            })
+            .method("echo", |_session, params| async move { Ok(params) })
     }
 
-    pub async fn send() {
-        let id = rand::random::<u64>();
-        // 使用 Arc<RwLock<HashMap<u64, ClientInfo>>> 来获取 CLIENTS
-        let clients = SOCKET_CLIENTS.clone();
-        // 使用 RwLock::write().await 来获取 CLIENTS 的写锁
-        let mut clients = clients.write().unwrap();
-        let mut reader = clients.get_mut(&id).unwrap();
-        let message = String::from("你好");
-        let _ = reader.stream.write(message.as_bytes()).await;
+    async fn write(stream: &mut TcpStream, message: &SocketMessageDTO) {
+        let codec = SocketCodec::new(FrameMode::Length, 65536);
+        stream.write_all(&codec.encode(&message.to_string()).unwrap()).await.unwrap();
     }
 
-}
\ No newline at end of file
+    async fn read(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> Option<SocketMessageDTO> {
+        let codec = SocketCodec::new(FrameMode::Length, 65536);
+        loop {
+            if let Some(frame) = codec.decode(buffer).unwrap() {
+                return Some(SocketMessageDTO::parse(&frame).unwrap());
+            }
+            let mut buf = [0u8; 4096];
+            let size = stream.read(&mut buf).await.unwrap_or(0);
+            if size == 0 {
+                return None;
+            }
+            buffer.extend_from_slice(&buf[..size]);
+        }
+    }
+
+    async fn connect(server: &SocketServer, account: &str) -> (TcpStream, Vec<u8>) {
+        connect_with(server, json!({"access_token": account})).await
+    }
+
+    /// 使用指定的握手信息（设备、标签等）建立连接并完成认证
+    async fn connect_with(server: &SocketServer, handshake: Value) -> (TcpStream, Vec<u8>) {
+        let codec = SocketCodec::new(FrameMode::Length, 65536);
+        let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+        let auth = SocketMessageDTO::new(SocketMessageType::Auth, handshake);
+        stream.write_all(&codec.encode(&auth.to_string()).unwrap()).await.unwrap();
+        let mut buffer = vec![];
+        let reply = read(&mut stream, &mut buffer).await.unwrap();
+        assert_eq!(reply.message_type, SocketMessageType::Reply);
+        (stream, buffer)
+    }
+
+    /// 认证失败、第一条消息不是auth以及超时未认证的连接都会收到错误并被断开
+    #[tokio::test]
+    async fn test_auth() {
+        let limits = SocketLimits { auth_timeout: Duration::from_millis(200), ..SocketLimits::default() };
+        let server = builder().limits(limits).start().await.unwrap();
+        let codec = SocketCodec::new(FrameMode::Length, 65536);
+        let rejected = [
+            Some(SocketMessageDTO::new(SocketMessageType::Auth, json!({"access_token": ""}))),
+            Some(SocketMessageDTO::new(SocketMessageType::Ping, Value::Null)),
+            None,
+        ];
+        for first in rejected {
+            let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+            if let Some(first) = &first {
+                stream.write_all(&codec.encode(&first.to_string()).unwrap()).await.unwrap();
+            }
+            let mut buffer = vec![];
+            let error = tokio::time::timeout(Duration::from_secs(1), read(&mut stream, &mut buffer)).await.unwrap().unwrap();
+            assert_eq!(error.message_type, SocketMessageType::Error);
+            assert!(error.response_result().err().unwrap().to_string().starts_with(&NOT_AUTHORIZE_CODE.to_string()));
+            assert!(read(&mut stream, &mut buffer).await.is_none());
+        }
+        assert!(server.registry().read().unwrap().is_empty());
+
+        // 认证成功后绑定账号
+        let (_alice, _) = connect(&server, "alice").await;
+        assert_eq!(server.registry().read().unwrap().find_by_account("alice").len(), 1);
+    }
+
+    /// 空闲的连接先收到服务端的ping，超过空闲超时后被断开；持续发送数据的连接不受影响
+    #[tokio::test]
+    async fn test_idle_eviction() {
+        let limits = SocketLimits {
+            heartbeat_interval: Duration::from_secs(1),
+            idle_timeout: Duration::from_secs(2),
+            ..SocketLimits::default()
+        };
+        let server = builder().limits(limits).start().await.unwrap();
+        let (mut alice, mut alice_buffer) = connect(&server, "alice").await;
+        let (mut bob, _) = connect(&server, "bob").await;
+        let keepalive = async {
+            loop {
+                write(&mut bob, &SocketMessageDTO::new(SocketMessageType::Ping, Value::Null)).await;
+                tokio::time::sleep(Duration::from_millis(300)).await;
+            }
+        };
+        let evicted = async {
+            let mut pinged = false;
+            while let Some(message) = read(&mut alice, &mut alice_buffer).await {
+                pinged |= message.message_type == SocketMessageType::Ping;
+            }
+            pinged
+        };
+        let pinged = tokio::select! {
+            _ = keepalive => unreachable!(),
+            pinged = tokio::time::timeout(Duration::from_secs(5), evicted) => pinged.unwrap(),
+        };
+        assert!(pinged);
+        let registry = server.registry();
+        assert!(registry.read().unwrap().find_by_account("alice").is_empty());
+        assert_eq!(registry.read().unwrap().find_by_account("bob").len(), 1);
+    }
+
+    /// 分组推送只发给同时满足组织、账号与标签条件的连接
+    #[tokio::test]
+    async fn test_multicast() {
+        // 账号以a开头的属于组织1，其余属于组织2
+        let server = SocketServerBuilder::new("127.0.0.1:0")
+            .authenticator(|handshake| async move {
+                let account = handshake.access_token.unwrap_or_default();
+                Ok(UserContext {
+                    organize: if account.starts_with('a') { 1 } else { 2 },
+                    account,
+                    name: String::new(),
+                    ip: String::new(),
+                    city: String::new(),
+                    leeway: 0,
+                })
+            })
+            .start()
+            .await
+            .unwrap();
+        let mut alice = connect_with(&server, json!({"access_token": "alice", "tags": ["vip", "ios"]})).await;
+        let mut amy = connect_with(&server, json!({"access_token": "amy", "tags": ["android"]})).await;
+        let mut bob = connect_with(&server, json!({"access_token": "bob", "tags": ["vip"]})).await;
+
+        let filter = |organize_id: Option<u64>, accounts: Option<Vec<&str>>, tags: Option<Vec<&str>>| SocketMulticastDTO {
+            organize_id,
+            accounts: accounts.map(|items| items.into_iter().map(String::from).collect()),
+            tags: tags.map(|items| items.into_iter().map(String::from).collect()),
+            payload: None,
+        };
+        let cases = [
+            (filter(Some(1), None, None), 2),
+            (filter(None, None, Some(vec!["vip"])), 2),
+            (filter(Some(1), None, Some(vec!["vip"])), 1),
+            (filter(None, Some(vec!["bob", "carol"]), None), 1),
+            (filter(Some(2), Some(vec!["alice"]), None), 0),
+        ];
+        for (index, (filter, matched)) in cases.iter().enumerate() {
+            let message = server.message(None, json!(index));
+            assert_eq!(server.send_matching(&message, |client| filter.matches(client)).unwrap(), (*matched, *matched));
+        }
+        // 最后广播一条结束标记，每个连接收到的消息即为命中的分组推送
+        assert_eq!(server.send_matching(&server.message(None, json!("end")), |_| true).unwrap(), (3, 3));
+        for ((stream, buffer), expected) in [(&mut alice, json!([0, 1, 2])), (&mut amy, json!([0])), (&mut bob, json!([1, 3]))] {
+            let mut received = vec![];
+            loop {
+                let push = read(stream, buffer).await.unwrap();
+                if push.payload == json!("end") {
+                    break;
+                }
+                received.push(push.payload);
+            }
+            assert_eq!(Value::Array(received), expected);
+        }
+    }
+
+    /// 同一账号同一设备再次登录时旧连接收到kick后断开，其他设备上的连接不受影响
+    #[tokio::test]
+    async fn test_replace() {
+        let server = start().await;
+        let (mut first, mut first_buffer) = connect(&server, "alice").await;
+        let (mut phone, mut phone_buffer) = connect_with(&server, json!({"access_token": "alice", "device": "phone"})).await;
+        let (_second, _) = connect(&server, "alice").await;
+        let kick = read(&mut first, &mut first_buffer).await.unwrap();
+        assert_eq!(kick.message_type, SocketMessageType::Kick);
+        assert_eq!(kick.payload, json!("账号在其他设备登录"));
+        assert!(read(&mut first, &mut first_buffer).await.is_none());
+        assert_eq!(server.registry().read().unwrap().find_by_account("alice").len(), 2);
+        write(&mut phone, &SocketMessageDTO::new(SocketMessageType::Ping, Value::Null)).await;
+        assert_eq!(read(&mut phone, &mut phone_buffer).await.unwrap().message_type, SocketMessageType::Pong);
+    }
+
+    /// 只允许向同名前缀账号发送消息的处理器
+    struct PrefixOnly;
+
+    impl MessageHandler for PrefixOnly {
+        fn authorize_message(&self, session: &SocketSession, to: &str) -> bool {
+            to.starts_with(&session.account[..1])
+        }
+    }
+
+    /// 客户端之间的消息在投递前经过处理器授权，未授权时回复 error 且不投递
+    #[tokio::test]
+    async fn test_authorize_message() {
+        let server = builder().handler(PrefixOnly).start().await.unwrap();
+        let (mut alice, mut alice_buffer) = connect(&server, "alice").await;
+        let (mut anna, mut anna_buffer) = connect(&server, "anna").await;
+        let message = |id: &str, to: &str| SocketMessageDTO {
+            id: Some(id.to_string()),
+            to: Some(to.to_string()),
+            ..SocketMessageDTO::new(SocketMessageType::Message, json!("hello"))
+        };
+        write(&mut alice, &message("1", "anna")).await;
+        let reply = read(&mut alice, &mut alice_buffer).await.unwrap();
+        assert_eq!(reply.message_type, SocketMessageType::Reply);
+        let push = read(&mut anna, &mut anna_buffer).await.unwrap();
+        assert_eq!(push.message_type, SocketMessageType::Push);
+        assert_eq!(push.from.as_deref(), Some("alice"));
+
+        write(&mut alice, &message("2", "bob")).await;
+        let error = read(&mut alice, &mut alice_buffer).await.unwrap();
+        assert_eq!(error.message_type, SocketMessageType::Error);
+        assert_eq!(error.id.as_deref(), Some("2"));
+        assert!(error.response_result().err().unwrap().to_string().starts_with(&NOT_AUTHORIZE_CODE.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_two_servers() {
+        let server_a = start().await;
+        let server_b = start().await;
+        let (mut alice, mut alice_buffer) = connect(&server_a, "alice").await;
+        let (_bob, _) = connect(&server_b, "bob").await;
+
+        // 两个服务的注册表互不影响
+        let message = server_a.message(None, Value::String("hello".to_string()));
+        assert_eq!(server_a.send("alice", &message).unwrap(), 1);
+        assert_eq!(server_a.send("bob", &message).unwrap(), 0);
+        assert_eq!(server_b.send("bob", &message).unwrap(), 1);
+        let push = read(&mut alice, &mut alice_buffer).await.unwrap();
+        assert_eq!(push.message_type, SocketMessageType::Push);
+        assert_eq!(push.id, Some(message.id.clone()));
+
+        server_a.shutdown(Duration::from_secs(1)).await;
+        assert!(server_a.registry().read().unwrap().is_empty());
+        let closing = read(&mut alice, &mut alice_buffer).await.unwrap();
+        assert_eq!(closing.message_type, SocketMessageType::ServerClosing);
+        assert!(read(&mut alice, &mut alice_buffer).await.is_none());
+        assert_eq!(server_b.registry().read().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_kick() {
+        let server = start().await;
+        let (mut alice, mut buffer) = connect(&server, "alice").await;
+        let id = server.registry().read().unwrap().find_by_account("alice")[0];
+        let ping = SocketMessageDTO::new(SocketMessageType::Ping, Value::Null).to_string();
+        alice.write_all(&SocketCodec::new(FrameMode::Length, 65536).encode(&ping).unwrap()).await.unwrap();
+        assert_eq!(read(&mut alice, &mut buffer).await.unwrap().message_type, SocketMessageType::Pong);
+        {
+            let registry = server.registry();
+            let clients = registry.read().unwrap();
+            let stats = &clients.get(&id).unwrap().stats;
+            assert_eq!(stats.frames_in.load(Ordering::Relaxed), 1);
+            assert_eq!(stats.bytes_in.load(Ordering::Relaxed), ping.len() as u64);
+            // 认证应答与pong
+            assert_eq!(stats.frames_out.load(Ordering::Relaxed), 2);
+        }
+
+        // 客户端先收到携带原因的kick消息，随后连接被断开
+        assert!(server.kick_client(id, "maintenance").await);
+        let kick = read(&mut alice, &mut buffer).await.unwrap();
+        assert_eq!(kick.message_type, SocketMessageType::Kick);
+        assert_eq!(kick.payload, json!("maintenance"));
+        assert!(read(&mut alice, &mut buffer).await.is_none());
+        assert!(server.registry().read().unwrap().is_empty());
+        assert!(!server.kick_client(id, "maintenance").await);
+    }
+
+    #[tokio::test]
+    async fn test_call() {
+        let server = start().await;
+        let (mut alice, mut buffer) = connect(&server, "alice").await;
+
+        // 服务端调用客户端的方法，客户端以相同id的 response 应答
+        let caller = server.clone();
+        let call = tokio::spawn(async move {
+            caller.call("alice", None, "status", json!({"verbose": true}), Duration::from_secs(5)).await
+        });
+        let request = read(&mut alice, &mut buffer).await.unwrap();
+        assert_eq!(request.message_type, SocketMessageType::Request);
+        assert_eq!(request.topic.as_deref(), Some("status"));
+        assert_eq!(request.payload, json!({"verbose": true}));
+        write(&mut alice, &SocketMessageDTO::response(request.id, &Ok(json!({"battery": 80})))).await;
+        assert_eq!(call.await.unwrap().unwrap(), json!({"battery": 80}));
+
+        // 未应答的调用超时后不再等待
+        assert!(server.call("alice", None, "status", Value::Null, Duration::from_millis(100)).await.is_err());
+        assert_eq!(read(&mut alice, &mut buffer).await.unwrap().message_type, SocketMessageType::Request);
+        let id = server.registry().read().unwrap().find_by_account("alice")[0];
+        assert!(server.registry().read().unwrap().get(&id).unwrap().pending_calls.lock().unwrap().is_empty());
+        assert!(server.call("bob", None, "status", Value::Null, Duration::from_secs(1)).await.is_err());
+
+        // 客户端调用服务端注册的方法
+        write(&mut alice, &SocketMessageDTO::request("1", "echo", json!("hi"))).await;
+        let response = read(&mut alice, &mut buffer).await.unwrap();
+        assert_eq!(response.message_type, SocketMessageType::Response);
+        assert_eq!(response.id.as_deref(), Some("1"));
+        assert_eq!(response.response_result().unwrap(), json!("hi"));
+        write(&mut alice, &SocketMessageDTO::request("2", "missing", Value::Null)).await;
+        let response = read(&mut alice, &mut buffer).await.unwrap();
+        assert_eq!(response.id.as_deref(), Some("2"));
+        assert!(response.response_result().err().unwrap().to_string().starts_with(&NOT_EXIST_CODE.to_string()));
+    }
+
+    /// 同一进程内的两个节点各自使用独立的集群节点，A节点投递的消息经redis转发给连接在B节点上的客户端
+    #[tokio::test]
+    #[ignore = "需要本地redis，使用 cargo test -- --ignored 运行"]
+    async fn test_cluster_deliver() {
+        let server_a = builder().cluster(SocketCluster::new("test-server-a", RedisClient::new(REDIS_URL))).start().await.unwrap();
+        let server_b = builder().cluster(SocketCluster::new("test-server-b", RedisClient::new(REDIS_URL))).start().await.unwrap();
+        // 每次使用不同的账号，避免残留的在线表影响结果
+        let account = format!("cluster-{:08x}", rand::random::<u32>());
+        let (mut carol, mut buffer) = connect(&server_b, &account).await;
+        // 等待两个节点订阅集群频道以及B节点登记账号在线
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let message = server_a.message(None, json!("hello"));
+        let delivery = server_a.deliver(&account, message.clone(), None).await.unwrap();
+        assert_eq!(delivery.connections, 0);
+        assert_eq!(delivery.nodes, 1);
+        let push = tokio::time::timeout(Duration::from_secs(1), read(&mut carol, &mut buffer)).await.unwrap().unwrap();
+        assert_eq!(push.message_type, SocketMessageType::Push);
+        assert_eq!(push.id, Some(message.id));
+
+        // 账号在B节点断开后登记下线，A节点不再转发
+        server_b.kick(&account, None, "test").await;
+        let delivery = server_a.deliver(&account, server_a.message(None, json!("bye")), None).await;
+        assert!(delivery.is_err());
+        server_a.shutdown(Duration::from_secs(1)).await;
+        server_b.shutdown(Duration::from_secs(1)).await;
+    }
+
+    /// 账号不在线时投递的消息转入离线存储，重新连接后在认证应答之后按顺序补发
+    #[tokio::test]
+    #[ignore = "需要 application.yml 中的redis可用，使用 cargo test -- --ignored 运行"]
+    async fn test_offline_flush() {
+        let server = builder().offline(true).start().await.unwrap();
+        let account = format!("offline-{:08x}", rand::random::<u32>());
+        let messages = [server.message(None, json!(1)), server.message(None, json!(2))];
+        for message in &messages {
+            let delivery = server.deliver(&account, message.clone(), None).await.unwrap();
+            assert_eq!(delivery.state, DeliveryState::Offline);
+        }
+        assert_eq!(CONTEXT.offline_message_service.list(&account).await.unwrap().len(), 2);
+
+        let (mut stream, mut buffer) = connect(&server, &account).await;
+        for message in &messages {
+            let push = read(&mut stream, &mut buffer).await.unwrap();
+            assert_eq!(push.message_type, SocketMessageType::Push);
+            assert_eq!(push.id, Some(message.id.clone()));
+        }
+        assert!(CONTEXT.offline_message_service.list(&account).await.unwrap().is_empty());
+        // 在线后的消息直接推送
+        let delivery = server.deliver(&account, server.message(None, json!(3)), None).await.unwrap();
+        assert_eq!(delivery.state, DeliveryState::Queued);
+        assert_eq!(read(&mut stream, &mut buffer).await.unwrap().payload, json!(3));
+
+        // 同一设备重新登录时，旧连接上未确认的消息转入离线消息并补发给新连接
+        let (mut again, mut again_buffer) = connect(&server, &account).await;
+        let mut payloads = vec![];
+        while payloads.len() < 3 {
+            payloads.push(read(&mut again, &mut again_buffer).await.unwrap().payload);
+        }
+        assert_eq!(payloads, vec![json!(1), json!(2), json!(3)]);
+        assert_eq!(read(&mut stream, &mut buffer).await.unwrap().message_type, SocketMessageType::Kick);
+    }
+
+    /// redis不可用时集群转发失败，当前节点上的投递不受影响
+    #[tokio::test]
+    async fn test_cluster_unavailable() {
+        let server = builder().cluster(SocketCluster::new("test-server-down", RedisClient::new("redis://127.0.0.1:1/"))).start().await.unwrap();
+        let (mut alice, mut buffer) = connect(&server, "alice").await;
+        let message = server.message(None, json!("hello"));
+        let delivery = server.deliver("alice", message.clone(), None).await.unwrap();
+        assert_eq!(delivery.connections, 1);
+        assert_eq!(delivery.nodes, 0);
+        assert_eq!(delivery.state, DeliveryState::Queued);
+        assert_eq!(read(&mut alice, &mut buffer).await.unwrap().id, Some(message.id));
+        let fanout = server.message(None, json!("all"));
+        assert_eq!(server.send_matching(&fanout, |_| true).unwrap(), (1, 1));
+        assert_eq!(server.relay_all(&ClusterEvent::Broadcast { message: fanout }).await, 0);
+    }
+}
@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use rustls_pemfile::Item;
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig, ServerName};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use crate::config::ApplicationConfig;
+use crate::util::error::Error;
+use crate::util::result::Result;
+
+/// socket 服务的TLS配置，证书与私钥均为PEM格式的文件
+#[derive(Debug, Clone)]
+pub struct SocketTls {
+    /// 证书链路径
+    pub cert: String,
+    /// 私钥路径
+    pub key: String,
+    /// 校验客户端证书的CA证书路径，为空时不要求客户端证书
+    pub client_ca: Option<String>,
+}
+
+impl SocketTls {
+    pub fn new(cert: &str, key: &str) -> Self {
+        Self {
+            cert: cert.to_string(),
+            key: key.to_string(),
+            client_ca: None,
+        }
+    }
+
+    /// 要求客户端出示由指定CA签发的证书
+    pub fn client_ca(mut self, client_ca: &str) -> Self {
+        self.client_ca = Some(client_ca.to_string());
+        self
+    }
+
+    /// 根据 application.yml 中的配置构造，未配置证书时返回 None
+    pub fn from_config(config: &ApplicationConfig) -> Option<Self> {
+        if config.socket_tls_cert.is_empty() {
+            return None;
+        }
+        let tls = Self::new(&config.socket_tls_cert, &config.socket_tls_key);
+        if config.socket_tls_client_ca.is_empty() {
+            Some(tls)
+        } else {
+            Some(tls.client_ca(&config.socket_tls_client_ca))
+        }
+    }
+
+    /// 读取证书与私钥，构造TLS握手使用的 TlsAcceptor
+    pub fn acceptor(&self) -> Result<TlsAcceptor> {
+        let certs = SocketTls::read_certs(&self.cert)?;
+        if certs.is_empty() {
+            return Err(Error::from(format!("TLS证书文件{}中没有证书", self.cert)));
+        }
+        let key = SocketTls::read_key(&self.key)?;
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let builder = match &self.client_ca {
+            Some(client_ca) => {
+                let roots = SocketTls::read_roots(client_ca)?;
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+            }
+            None => builder.with_no_client_auth(),
+        };
+        match builder.with_single_cert(certs, key) {
+            Ok(config) => Ok(TlsAcceptor::from(Arc::new(config))),
+            Err(e) => Err(Error::from(format!("TLS证书{}与私钥{}无效:{}", self.cert, self.key, e))),
+        }
+    }
+
+    /// 读取CA证书文件构造信任的根证书
+    fn read_roots(path: &str) -> Result<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+        for cert in SocketTls::read_certs(path)? {
+            if let Err(e) = roots.add(&cert) {
+                return Err(Error::from(format!("CA证书文件{}无效:{}", path, e)));
+            }
+        }
+        if roots.is_empty() {
+            return Err(Error::from(format!("CA证书文件{}中没有证书", path)));
+        }
+        Ok(roots)
+    }
+
+    /// 读取PEM文件中的全部证书
+    fn read_certs(path: &str) -> Result<Vec<Certificate>> {
+        let items = SocketTls::read_pem(path)?;
+        Ok(items
+            .into_iter()
+            .filter_map(|item| match item {
+                Item::X509Certificate(der) => Some(Certificate(der)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// 读取PEM文件中的第一个私钥
+    fn read_key(path: &str) -> Result<PrivateKey> {
+        let key = SocketTls::read_pem(path)?.into_iter().find_map(|item| match item {
+            Item::PKCS8Key(der) | Item::RSAKey(der) | Item::ECKey(der) => Some(PrivateKey(der)),
+            _ => None,
+        });
+        match key {
+            Some(key) => Ok(key),
+            None => Err(Error::from(format!("TLS私钥文件{}中没有私钥", path))),
+        }
+    }
+
+    fn read_pem(path: &str) -> Result<Vec<Item>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => return Err(Error::from(format!("读取文件{}失败:{}", path, e))),
+        };
+        match rustls_pemfile::read_all(&mut BufReader::new(file)) {
+            Ok(items) => Ok(items),
+            Err(e) => Err(Error::from(format!("解析PEM文件{}失败:{}", path, e))),
+        }
+    }
+}
+
+/// socket 客户端的TLS配置，证书与私钥均为PEM格式的文件
+#[derive(Debug, Clone)]
+pub struct SocketClientTls {
+    /// 校验服务端证书的CA证书路径
+    pub ca: String,
+    /// 校验服务端证书使用的域名，为空时使用连接地址中的主机名
+    pub server_name: Option<String>,
+    /// 出示给服务端的客户端证书链与私钥路径，服务端配置了 client_ca 时需要
+    pub identity: Option<(String, String)>,
+}
+
+impl SocketClientTls {
+    pub fn new(ca: &str) -> Self {
+        Self {
+            ca: ca.to_string(),
+            server_name: None,
+            identity: None,
+        }
+    }
+
+    /// 校验服务端证书时使用指定的域名，而不是连接地址中的主机名
+    pub fn server_name(mut self, server_name: &str) -> Self {
+        self.server_name = Some(server_name.to_string());
+        self
+    }
+
+    /// 向服务端出示客户端证书
+    pub fn identity(mut self, cert: &str, key: &str) -> Self {
+        self.identity = Some((cert.to_string(), key.to_string()));
+        self
+    }
+
+    /// 读取证书，构造TLS握手使用的 TlsConnector
+    pub fn connector(&self) -> Result<TlsConnector> {
+        let roots = SocketTls::read_roots(&self.ca)?;
+        let builder = ClientConfig::builder().with_safe_defaults().with_root_certificates(roots);
+        let config = match &self.identity {
+            Some((cert, key)) => {
+                let certs = SocketTls::read_certs(cert)?;
+                if certs.is_empty() {
+                    return Err(Error::from(format!("TLS证书文件{}中没有证书", cert)));
+                }
+                match builder.with_client_auth_cert(certs, SocketTls::read_key(key)?) {
+                    Ok(config) => config,
+                    Err(e) => return Err(Error::from(format!("TLS证书{}与私钥{}无效:{}", cert, key, e))),
+                }
+            }
+            None => builder.with_no_client_auth(),
+        };
+        Ok(TlsConnector::from(Arc::new(config)))
+    }
+
+    /// 校验服务端证书使用的名称，未指定 server_name 时取连接地址中的主机名（域名或IP）
+    pub fn server_name_for(&self, address: &str) -> Result<ServerName> {
+        let name = match &self.server_name {
+            Some(name) => name.as_str(),
+            None => address.rsplit_once(':').map(|(host, _)| host).unwrap_or(address).trim_start_matches('[').trim_end_matches(']'),
+        };
+        match ServerName::try_from(name) {
+            Ok(name) => Ok(name),
+            Err(e) => Err(Error::from(format!("无效的TLS服务端名称{}:{}", name, e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::socket_tls::{SocketClientTls, SocketTls};
+
+    #[test]
+    fn test_missing_files() {
+        // 证书文件不存在时返回错误，而不是退化为明文连接
+        let error = SocketTls::new("target/missing-cert.pem", "target/missing-key.pem").acceptor().err().unwrap();
+        assert!(error.to_string().contains("target/missing-cert.pem"));
+    }
+
+    #[test]
+    fn test_client_server_name() {
+        let tls = SocketClientTls::new("target/missing-ca.pem");
+        assert!(tls.connector().err().unwrap().to_string().contains("target/missing-ca.pem"));
+        assert!(tls.server_name_for("127.0.0.1:9000").is_ok());
+        assert!(tls.server_name_for("[::1]:9000").is_ok());
+        assert!(tls.server_name_for("socket.example.com:9000").is_ok());
+        assert!(tls.server_name_for("bad name:9000").is_err());
+        assert!(tls.clone().server_name("socket.example.com").server_name_for("bad name:9000").is_ok());
+    }
+}
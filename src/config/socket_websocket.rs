@@ -0,0 +1,384 @@
+use std::sync::Arc;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_http::ws::Item;
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use tokio::sync::watch;
+use crate::config::socket_guard::ConnectionPermit;
+use crate::config::socket_outbound::OutboundQueue;
+use crate::config::socket_server::SocketServer;
+use crate::config::user_context::UserContext;
+use crate::domain::dto::socket_client_info::SocketStats;
+use crate::domain::dto::socket_handshake::SocketHandshakeDTO;
+use crate::domain::dto::socket_message::SocketMessageDTO;
+use crate::domain::vo::RespVO;
+use crate::util::constant::{BAD_REQUEST_ERROR_CODE, NOT_AUTHORIZE_CODE};
+use crate::util::error::Error;
+use crate::util::result::Result;
+
+/// WebSocket 接入：浏览器客户端通过 WebSocket 连接到 socket 服务，与TCP客户端共用注册表、主题与推送接口；
+/// 每个文本（或UTF-8二进制）消息对应TCP连接中的一帧，内容同样是 SocketMessageDTO 信封；分片发送的消息拼接完整后再处理
+pub struct SocketWebsocket {}
+
+impl SocketWebsocket {
+
+    /// 把http请求升级为WebSocket连接，连接建立后第一条消息必须是 auth
+    pub fn upgrade(server: SocketServer, req: &HttpRequest, body: web::Payload) -> actix_web::Result<HttpResponse> {
+        if server.is_shutdown() {
+            return Ok(HttpResponse::ServiceUnavailable().finish());
+        }
+        // 与TCP连接共用连接数上限与黑白名单，按直连的对端地址（而非可伪造的转发头）检查
+        let permit = match req.peer_addr() {
+            Some(peer_addr) => match server.admit(peer_addr.ip()) {
+                Ok(permit) => permit,
+                Err(e) => {
+                    log::warn!("拒绝WebSocket客户端 {} 的连接:{}", peer_addr, e);
+                    return Ok(RespVO::<String>::from_result(&Err(e)).resp_json());
+                }
+            },
+            None => return Ok(HttpResponse::BadRequest().finish()),
+        };
+        let (response, session, stream) = actix_ws::handle(req, body)?;
+        let peer_addr = req.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+        // MessageStream 只能在当前的actix工作线程上读取
+        actix_web::rt::spawn(SocketWebsocket::handle_connection(server, session, stream, peer_addr, permit));
+        Ok(response)
+    }
+
+    /// 处理单个WebSocket连接：先完成握手认证，再注册到注册表并循环接收消息
+    async fn handle_connection(server: SocketServer, mut ws: Session, mut stream: MessageStream, peer_addr: String, _permit: ConnectionPermit) {
+        log::info!("WebSocket accepted from: {}", peer_addr);
+        let auth_timeout = server.limits().auth_timeout;
+        // 尚未接收完的分片消息，认证与后续消息共用
+        let mut fragments = None;
+        let handshake = tokio::time::timeout(
+            auth_timeout,
+            SocketWebsocket::authenticate(&server, &mut ws, &mut stream, &mut fragments),
+        ).await;
+        let (user, handshake, auth_id) = match handshake {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => {
+                log::error!("WebSocket客户端 {} 认证失败:{}", peer_addr, e);
+                SocketWebsocket::reject(ws, &e).await;
+                return;
+            }
+            Err(_) => {
+                log::error!("WebSocket客户端 {} 在{}秒内未完成认证", peer_addr, auth_timeout.as_secs());
+                SocketWebsocket::reject(ws, &Error::from(("认证超时", NOT_AUTHORIZE_CODE))).await;
+                return;
+            }
+        };
+        let mut connection = server.open(user, handshake, auth_id, &peer_addr).await;
+        let id = connection.session.id;
+        tokio::spawn(SocketWebsocket::write_loop(id, ws.clone(), connection.queue.clone(), connection.closer.clone(), connection.stats.clone()));
+        let closed = connection.closer.subscribe();
+        // 循环接收客户端消息
+        loop {
+            let message = tokio::select! {
+                // 连接被服务端断开（踢下线、队列溢出、写入失败等）
+                _ = SocketServer::closed(closed.clone()) => break,
+                message = stream.recv() => match message {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        log::error!("WebSocket客户端 {} 数据接收异常:{}", id, e);
+                        break;
+                    }
+                    None => break,
+                },
+            };
+            let frame = match SocketWebsocket::frame(&server, message, &mut fragments) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => {
+                    server.touch(id);
+                    continue;
+                }
+                Err(e) => {
+                    log::error!("WebSocket客户端 {} 数据接收异常:{}", id, e);
+                    break;
+                }
+            };
+            match frame {
+                WebsocketFrame::Text(frame) => {
+                    if !server.receive(&mut connection, &frame).await {
+                        break;
+                    }
+                }
+                WebsocketFrame::Ping(payload) => {
+                    server.touch(id);
+                    let _ = ws.pong(&payload).await;
+                }
+                WebsocketFrame::Close => break,
+            }
+        }
+        server.disconnect(connection).await;
+    }
+
+    /// 读取 auth 类型的握手消息并完成认证
+    async fn authenticate(server: &SocketServer, ws: &mut Session, stream: &mut MessageStream, fragments: &mut Option<Vec<u8>>) -> Result<(UserContext, SocketHandshakeDTO, Option<String>)> {
+        loop {
+            let message = match stream.recv().await {
+                Some(Ok(message)) => message,
+                Some(Err(e)) => return Err(Error::from(format!("数据接收异常:{}", e))),
+                None => return Err(Error::from("客户端在认证前断开连接")),
+            };
+            match SocketWebsocket::frame(server, message, fragments)? {
+                Some(WebsocketFrame::Text(frame)) => return server.login(&frame).await,
+                Some(WebsocketFrame::Ping(payload)) => {
+                    let _ = ws.pong(&payload).await;
+                }
+                Some(WebsocketFrame::Close) => return Err(Error::from("客户端在认证前断开连接")),
+                None => {}
+            }
+        }
+    }
+
+    /// 把WebSocket消息转换为信封帧，返回 None 表示无需处理的控制消息或尚未接收完的分片；
+    /// 分片暂存在 fragments 中，拼接后的长度同样受单帧上限限制
+    fn frame(server: &SocketServer, message: Message, fragments: &mut Option<Vec<u8>>) -> Result<Option<WebsocketFrame>> {
+        let frame = match message {
+            Message::Text(text) => text.to_string(),
+            Message::Binary(bytes) => SocketWebsocket::text(bytes.to_vec())?,
+            Message::Continuation(item) => {
+                let (first, last, bytes) = match item {
+                    Item::FirstText(bytes) | Item::FirstBinary(bytes) => (true, false, bytes),
+                    Item::Continue(bytes) => (false, false, bytes),
+                    Item::Last(bytes) => (false, true, bytes),
+                };
+                if first != fragments.is_none() {
+                    return Err(Error::from(("分片消息的顺序不正确", BAD_REQUEST_ERROR_CODE)));
+                }
+                let buffer = fragments.get_or_insert_with(Vec::new);
+                let max_frame_size = server.codec().max_frame_size;
+                if buffer.len() + bytes.len() > max_frame_size {
+                    return Err(Error::from((
+                        format!("分片消息长度超出单帧上限{}", max_frame_size),
+                        BAD_REQUEST_ERROR_CODE,
+                    )));
+                }
+                buffer.extend_from_slice(&bytes);
+                if !last {
+                    return Ok(None);
+                }
+                SocketWebsocket::text(fragments.take().unwrap_or_default())?
+            }
+            Message::Ping(payload) => return Ok(Some(WebsocketFrame::Ping(payload.to_vec()))),
+            Message::Close(_) => return Ok(Some(WebsocketFrame::Close)),
+            Message::Pong(_) | Message::Nop => return Ok(None),
+        };
+        // 与TCP连接相同的单帧大小限制
+        server.codec().validate(&frame)?;
+        Ok(Some(WebsocketFrame::Text(frame)))
+    }
+
+    /// 二进制消息与分片拼接后的内容必须是UTF-8编码
+    fn text(bytes: Vec<u8>) -> Result<String> {
+        match String::from_utf8(bytes) {
+            Ok(text) => Ok(text),
+            Err(e) => Err(Error::from(format!("二进制消息不是UTF-8编码:{}", e))),
+        }
+    }
+
+    /// 向未通过认证的客户端回复错误消息并断开连接
+    async fn reject(mut ws: Session, error: &Error) {
+        let _ = ws.text(SocketMessageDTO::error(None, error).to_string()).await;
+        let _ = ws.close(Some(CloseReason::from((CloseCode::Policy, error.to_string())))).await;
+    }
+
+    /// 连接的写任务，按顺序把发送队列中的消息作为文本消息发送给客户端
+    async fn write_loop(id: u64, mut ws: Session, queue: Arc<OutboundQueue>, closer: Arc<watch::Sender<bool>>, stats: Arc<SocketStats>) {
+        let closed = closer.subscribe();
+        loop {
+            let message = tokio::select! {
+                _ = SocketServer::closed(closed.clone()) => break,
+                message = queue.pop() => match message {
+                    Some(message) => message,
+                    None => break,
+                },
+            };
+            let size = message.len();
+            if ws.text(message).await.is_err() {
+                log::error!("向WebSocket客户端 {} 发送消息失败:连接已关闭", id);
+                break;
+            }
+            stats.sent(size);
+        }
+        queue.close();
+        let _ = ws.close(Some(CloseReason::from(CloseCode::Normal))).await;
+        closer.send_replace(true);
+    }
+}
+
+/// 需要处理的WebSocket消息
+enum WebsocketFrame {
+    /// 信封帧
+    Text(String),
+    Ping(Vec<u8>),
+    Close,
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use actix_web::{web, App, HttpRequest, HttpServer};
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use crate::config::socket_builder::SocketServerBuilder;
+    use crate::config::socket_server::SocketServer;
+    use crate::config::socket_websocket::SocketWebsocket;
+    use crate::config::user_context::UserContext;
+    use crate::domain::dto::socket_message::{SocketMessageDTO, SocketMessageType};
+    use crate::util::constant::NOT_AUTHORIZE_CODE;
+    use crate::util::error::Error;
+
+    /// WebSocket 关闭帧的操作码
+    const CLOSE: u8 = 0x8;
+
+    async fn start() -> SocketServer {
+        SocketServerBuilder::new("127.0.0.1:0")
+            .authenticator(|handshake| async move {
+                let account = handshake.access_token.unwrap_or_default();
+                if account.is_empty() {
+                    return Err(Error::from(("access_token不能为空", NOT_AUTHORIZE_CODE)));
+                }
+                Ok(UserContext {
+                    account,
+                    name: String::new(),
+                    organize: 0,
+                    ip: String::new(),
+                    city: String::new(),
+                    leeway: 0,
+                })
+            })
+            .start()
+            .await
+            .unwrap()
+    }
+
+    /// 启动只有 /ws 接口的http服务，返回监听的地址
+    fn serve(server: SocketServer) -> SocketAddr {
+        let http = HttpServer::new(move || {
+            let server = server.clone();
+            App::new().route("/ws", web::get().to(move |req: HttpRequest, body: web::Payload| {
+                let server = server.clone();
+                async move { SocketWebsocket::upgrade(server, &req, body) }
+            }))
+        })
+            .workers(1)
+            .disable_signals()
+            .bind("127.0.0.1:0")
+            .unwrap();
+        let address = http.addrs()[0];
+        actix_web::rt::spawn(http.run());
+        address
+    }
+
+    /// 发送升级请求并读完101应答
+    async fn open(address: SocketAddr) -> TcpStream {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let request = format!(
+            "GET /ws HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n",
+            address
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = vec![];
+        while !response.ends_with(b"\r\n\r\n") {
+            response.push(stream.read_u8().await.unwrap());
+        }
+        assert!(String::from_utf8_lossy(&response).starts_with("HTTP/1.1 101"));
+        stream
+    }
+
+    /// 发送一条文本消息
+    async fn write(stream: &mut TcpStream, message: &SocketMessageDTO) {
+        write_frame(stream, 0x81, message.to_string().as_bytes()).await;
+    }
+
+    /// 发送一帧，head 为FIN位与操作码，客户端发出的帧必须带掩码
+    async fn write_frame(stream: &mut TcpStream, head: u8, payload: &[u8]) {
+        let mask = [0x12u8, 0x34, 0x56, 0x78];
+        let mut frame = vec![head];
+        if payload.len() < 126 {
+            frame.push(0x80 | payload.len() as u8);
+        } else {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(index, byte)| byte ^ mask[index % 4]));
+        stream.write_all(&frame).await.unwrap();
+    }
+
+    /// 读取服务端发来的一帧，返回（操作码，内容），连接关闭时返回 None
+    async fn read_frame(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+        let mut head = [0u8; 2];
+        stream.read_exact(&mut head).await.ok()?;
+        let size = match head[1] & 0x7f {
+            126 => stream.read_u16().await.ok()? as usize,
+            127 => stream.read_u64().await.ok()? as usize,
+            size => size as usize,
+        };
+        let mut payload = vec![0u8; size];
+        stream.read_exact(&mut payload).await.ok()?;
+        Some((head[0] & 0x0f, payload))
+    }
+
+    /// 读取一条文本消息并解析信封
+    async fn read(stream: &mut TcpStream) -> SocketMessageDTO {
+        let (opcode, payload) = tokio::time::timeout(Duration::from_secs(5), read_frame(stream)).await.unwrap().unwrap();
+        assert_eq!(opcode, 0x1);
+        SocketMessageDTO::parse(&String::from_utf8(payload).unwrap()).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_gateway() {
+        let server = start().await;
+        let address = serve(server.clone());
+
+        // 认证失败时先收到错误消息，随后连接被关闭
+        let mut stream = open(address).await;
+        write(&mut stream, &SocketMessageDTO::new(SocketMessageType::Auth, json!({"access_token": ""}))).await;
+        let error = read(&mut stream).await;
+        assert_eq!(error.message_type, SocketMessageType::Error);
+        assert!(error.response_result().err().unwrap().to_string().starts_with(&NOT_AUTHORIZE_CODE.to_string()));
+        assert_eq!(read_frame(&mut stream).await.unwrap().0, CLOSE);
+
+        // 认证成功后与TCP连接共用注册表，可以收到服务端的推送
+        let mut stream = open(address).await;
+        write(&mut stream, &SocketMessageDTO::new(SocketMessageType::Auth, json!({"access_token": "alice"}))).await;
+        assert_eq!(read(&mut stream).await.message_type, SocketMessageType::Reply);
+        assert_eq!(server.registry().read().unwrap().find_by_account("alice").len(), 1);
+        let message = server.message(None, json!("hello"));
+        assert_eq!(server.send("alice", &message).unwrap(), 1);
+        let push = read(&mut stream).await;
+        assert_eq!(push.message_type, SocketMessageType::Push);
+        assert_eq!(push.id, Some(message.id));
+        write(&mut stream, &SocketMessageDTO::new(SocketMessageType::Ping, Value::Null)).await;
+        assert_eq!(read(&mut stream).await.message_type, SocketMessageType::Pong);
+
+        // 分三个分片发送的消息拼接完整后再处理
+        let ping = SocketMessageDTO::new(SocketMessageType::Ping, Value::Null).to_string().into_bytes();
+        write_frame(&mut stream, 0x01, &ping[..4]).await;
+        write_frame(&mut stream, 0x00, &ping[4..8]).await;
+        write_frame(&mut stream, 0x80, &ping[8..]).await;
+        assert_eq!(read(&mut stream).await.message_type, SocketMessageType::Pong);
+
+        // 被踢下线时先收到kick消息，随后连接被关闭并从注册表移除
+        assert_eq!(server.kick("alice", None, "bye").await, 1);
+        let kick = read(&mut stream).await;
+        assert_eq!(kick.message_type, SocketMessageType::Kick);
+        assert_eq!(kick.payload, json!("bye"));
+        assert_eq!(read_frame(&mut stream).await.unwrap().0, CLOSE);
+        assert!(server.registry().read().unwrap().is_empty());
+
+        // 分片拼接后超出单帧上限时断开连接
+        let mut stream = open(address).await;
+        write(&mut stream, &SocketMessageDTO::new(SocketMessageType::Auth, json!({"access_token": "bob"}))).await;
+        assert_eq!(read(&mut stream).await.message_type, SocketMessageType::Reply);
+        let fragment = vec![b' '; 40000];
+        write_frame(&mut stream, 0x01, &fragment).await;
+        write_frame(&mut stream, 0x80, &fragment).await;
+        let close = tokio::time::timeout(Duration::from_secs(5), read_frame(&mut stream)).await.unwrap();
+        assert_eq!(close.unwrap().0, CLOSE);
+    }
+}
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use actix_web::{get, web, Responder, post, put, HttpRequest, delete};
 use crate::domain::dto::user::{UserDTO, UserPageDTO};
+use crate::domain::dto::socket_multicast::SocketMulticastDTO;
+use crate::domain::dto::socket_push::SocketSendQueryDTO;
 use crate::config::CONTEXT;
 use crate::domain::vo::RespVO;
 use serde_json::Value;
@@ -19,6 +21,53 @@ pub async fn send_mail_message(path: web::Path<(String, String)>,arg: web::Json<
     return RespVO::from_result(&vo).resp_json();
 }
 
+/// 向账号推送socket消息，可通过 wait_ack 参数指定等待客户端确认的秒数；
+/// 白名单按完整路径匹配，该接口不在白名单中，需要登录
+#[post("/send/socket/{account}")]
+pub async fn send_socket_message(path: web::Path<String>, query: web::Query<SocketSendQueryDTO>, arg: web::Json<Value>) -> impl Responder {
+    let account = path.into_inner();
+    let vo = CONTEXT.user_service.send_socket_message(&account, &arg.0, query.wait_ack).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
+/// 向所有在线的socket客户端广播消息
+#[post("/broadcast")]
+pub async fn broadcast_socket_message(arg: web::Json<Value>) -> impl Responder {
+    let vo = CONTEXT.socket_service.broadcast(&arg.0).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
+/// 按组织、账号或标签向部分socket客户端推送消息
+#[post("/multicast")]
+pub async fn multicast_socket_message(arg: web::Json<SocketMulticastDTO>) -> impl Responder {
+    let vo = CONTEXT.socket_service.multicast(&arg.0).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
+/// 向订阅了主题的socket客户端发布消息
+#[post("/publish/{topic}")]
+pub async fn publish_socket_message(path: web::Path<String>,arg: web::Json<Value>) -> impl Responder {
+    let topic = path.into_inner();
+    let vo = CONTEXT.socket_service.publish(&topic,&arg.0).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
+/// 查看账号待发送的socket离线消息
+#[get("/offline/{account}")]
+pub async fn offline_message_list(path: web::Path<String>) -> impl Responder {
+    let account = path.into_inner();
+    let vo = CONTEXT.offline_message_service.list(&account).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
+/// 清空账号待发送的socket离线消息
+#[delete("/offline/{account}")]
+pub async fn offline_message_purge(path: web::Path<String>) -> impl Responder {
+    let account = path.into_inner();
+    let vo = CONTEXT.offline_message_service.purge(&account).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
 // /// 获取用户分页列表
 // #[get("/user/page")]
 // pub async fn user_page(arg: web::Json<UserPageDTO>) -> impl Responder {
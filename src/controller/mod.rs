@@ -1,3 +1,4 @@
 /// 接口模块
 
-pub mod message_controller;
\ No newline at end of file
+pub mod message_controller;
+pub mod socket_controller;
\ No newline at end of file
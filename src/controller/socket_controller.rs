@@ -0,0 +1,62 @@
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use crate::config::CONTEXT;
+use crate::config::socket_websocket::SocketWebsocket;
+use crate::domain::dto::socket_admin::{SocketCallDTO, SocketClientPageDTO, SocketKickDTO};
+use crate::domain::vo::RespVO;
+use serde_json::Value;
+
+/// 浏览器客户端的WebSocket接入，与TCP客户端使用相同的消息信封，连接建立后第一条消息必须是 auth
+#[get("/ws")]
+pub async fn websocket(req: HttpRequest, body: web::Payload) -> actix_web::Result<HttpResponse> {
+    let server = match CONTEXT.socket_server() {
+        Ok(server) => server,
+        Err(e) => return Ok(RespVO::<String>::from_result(&Err(e)).resp_json()),
+    };
+    SocketWebsocket::upgrade(server, &req, body)
+}
+
+/// 分页查询当前节点上的socket连接，可按账号、主题过滤
+#[get("/clients")]
+pub async fn client_page(arg: web::Query<SocketClientPageDTO>) -> impl Responder {
+    let vo = CONTEXT.socket_service.client_page(&arg.0).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
+/// 查询socket连接的详情与收发统计
+#[get("/clients/{id}")]
+pub async fn client_detail(path: web::Path<u64>) -> impl Responder {
+    let vo = CONTEXT.socket_service.client_detail(path.into_inner()).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
+/// 断开socket连接，可通过 reason 参数指定告知客户端的原因
+#[delete("/clients/{id}")]
+pub async fn kick_client(path: web::Path<u64>, arg: web::Query<SocketKickDTO>) -> impl Responder {
+    let vo = CONTEXT.socket_service.kick_client(path.into_inner(), &arg.0).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
+/// 断开账号在所有节点上的socket连接，可通过 reason 参数指定告知客户端的原因
+#[delete("/accounts/{account}")]
+pub async fn kick_account(path: web::Path<String>, arg: web::Query<SocketKickDTO>) -> impl Responder {
+    let account = path.into_inner();
+    let vo = CONTEXT.socket_service.kick_account(&account, &arg.0).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
+/// 调用账号socket连接上的方法，请求体为调用参数，等待客户端应答后返回应答内容；
+/// 可通过 device 参数指定设备，通过 timeout 参数指定等待应答的秒数
+#[post("/accounts/{account}/call/{method}")]
+pub async fn call_account(path: web::Path<(String, String)>, query: web::Query<SocketCallDTO>, arg: web::Json<Value>) -> impl Responder {
+    let (account, method) = path.into_inner();
+    let vo = CONTEXT.socket_service.call_account(&account, &method, &query.0, &arg.0).await;
+    return RespVO::from_result(&vo).resp_json();
+}
+
+/// 调用socket连接上的方法，请求体为调用参数，等待客户端应答后返回应答内容
+#[post("/clients/{id}/call/{method}")]
+pub async fn call_client(path: web::Path<(u64, String)>, query: web::Query<SocketCallDTO>, arg: web::Json<Value>) -> impl Responder {
+    let (id, method) = path.into_inner();
+    let vo = CONTEXT.socket_service.call_client(id, &method, &query.0, &arg.0).await;
+    return RespVO::from_result(&vo).resp_json();
+}
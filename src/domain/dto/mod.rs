@@ -3,3 +3,8 @@
 pub mod page;
 pub mod user;
 pub mod socket_client_info;
+pub mod socket_handshake;
+pub mod socket_multicast;
+pub mod socket_push;
+pub mod socket_message;
+pub mod socket_admin;
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// socket 连接分页查询参数
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SocketClientPageDTO {
+    pub page_no: Option<u64>,
+    pub page_size: Option<u64>,
+    /// 按账号过滤
+    pub account: Option<String>,
+    /// 按订阅的主题过滤
+    pub topic: Option<String>,
+}
+
+/// 断开socket连接的参数
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SocketKickDTO {
+    /// 断开原因，通过 kick 消息告知客户端
+    pub reason: Option<String>,
+}
+
+/// 远程调用socket客户端的参数
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SocketCallDTO {
+    /// 调用账号指定设备上的连接，为空时调用账号最近建立的连接
+    pub device: Option<String>,
+    /// 等待客户端应答的秒数，为空时使用 socket_call_timeout
+    pub timeout: Option<u64>,
+}
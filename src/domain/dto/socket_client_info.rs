@@ -1,9 +1,296 @@
-use async_std::net::TcpStream;
-use std::collections::HashMap;
-use std::sync::RwLock;
+use chrono::{DateTime, FixedOffset, TimeZone};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde_json::Value;
+use tokio::sync::{oneshot, watch};
+use crate::config::socket_outbound::{EnqueueResult, OutboundQueue};
+use crate::domain::dto::socket_message::SocketMessageDTO;
+use crate::domain::dto::socket_push::SocketPushDTO;
+use crate::util::date_time::DateUtils;
+use crate::util::error::Error;
+use crate::util::result::Result;
 
 // 定义客户端信息结构体
 pub struct SocketClientInfo {
-    pub stream: TcpStream,
-    pub lock: RwLock<HashMap<u64, SocketClientInfo>>,
-}
\ No newline at end of file
+    /// 连接id，由注册表分配
+    pub id: u64,
+    /// 断开连接的信号，连接的读写任务收到后立即结束
+    pub closer: Arc<watch::Sender<bool>>,
+    /// 发送队列，由连接的写任务负责消费
+    pub queue: Arc<OutboundQueue>,
+    /// 握手后绑定的账号
+    pub account: Option<String>,
+    /// 握手后绑定的设备id（同一账号可以有多个设备）
+    pub device: Option<String>,
+    /// 账号所属组织
+    pub organize: Option<u64>,
+    /// 握手时客户端声明的标签，用于分组推送
+    pub tags: HashSet<String>,
+    /// 已订阅的主题
+    pub topics: HashSet<String>,
+    /// 客户端地址
+    pub peer_addr: String,
+    /// 建立连接的时间
+    pub connect_time: DateTime<FixedOffset>,
+    /// 最后一次收到数据的时间（毫秒时间戳），每收到一帧都会刷新，只需持有注册表的读锁
+    pub last_active: AtomicI64,
+    /// 推送消息的序号
+    pub sequence: AtomicU64,
+    /// 已推送但尚未收到客户端确认的消息，key为消息id
+    pub pending_acks: Mutex<HashMap<String, PendingAck>>,
+    /// 服务端发起、尚未收到客户端应答的远程调用，key为调用id；连接断开时随之丢弃，调用方收到连接已断开的错误
+    pub pending_calls: Mutex<HashMap<String, oneshot::Sender<Result<Value>>>>,
+    /// 连接的收发统计，由连接的读写任务更新
+    pub stats: Arc<SocketStats>,
+}
+
+/// socket 连接的收发统计
+#[derive(Default)]
+pub struct SocketStats {
+    /// 收到的帧数
+    pub frames_in: AtomicU64,
+    /// 收到的字节数（消息体）
+    pub bytes_in: AtomicU64,
+    /// 发送的帧数
+    pub frames_out: AtomicU64,
+    /// 发送的字节数（消息体）
+    pub bytes_out: AtomicU64,
+    /// 发送队列溢出丢弃的消息数
+    pub dropped: AtomicU64,
+    /// 超过速率限制被限速的帧数
+    pub throttled: AtomicU64,
+}
+
+impl SocketStats {
+    pub fn received(&self, size: usize) {
+        self.frames_in.fetch_add(1, Ordering::Relaxed);
+        self.bytes_in.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    pub fn sent(&self, size: usize) {
+        self.frames_out.fetch_add(1, Ordering::Relaxed);
+        self.bytes_out.fetch_add(size as u64, Ordering::Relaxed);
+    }
+}
+
+/// 等待客户端确认的推送消息
+pub struct PendingAck {
+    pub message: SocketPushDTO,
+    /// 已编码的消息，重发时直接使用
+    pub frame: String,
+    /// 已重发的次数
+    pub attempts: u32,
+    /// 最近一次发送的时间
+    pub sent_time: Instant,
+    /// 等待确认结果的调用方
+    pub waiter: Option<oneshot::Sender<bool>>,
+}
+
+/// 将消息放入连接的发送队列，溢出丢弃的消息计入统计，队列溢出且策略为断开时关闭连接；
+/// SocketClientInfo 与 SocketSession 发送消息都经过这里
+pub(crate) fn enqueue(id: u64, queue: &OutboundQueue, stats: &SocketStats, closer: &watch::Sender<bool>, message: String) -> EnqueueResult {
+    let result = queue.push(message);
+    match result {
+        EnqueueResult::DroppedOldest | EnqueueResult::DroppedNewest => {
+            stats.dropped.fetch_add(1, Ordering::Relaxed);
+            log::warn!("客户端 {} 发送队列已满，丢弃消息({:?})", id, result);
+        }
+        EnqueueResult::Disconnect => {
+            log::warn!("客户端 {} 发送队列已满，断开连接", id);
+            closer.send_replace(true);
+        }
+        _ => {}
+    }
+    result
+}
+
+impl SocketClientInfo {
+    /// 刷新最后活跃时间
+    pub fn touch(&self) {
+        self.last_active.store(DateUtils::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    /// 最后一次收到数据的时间
+    pub fn last_active_time(&self) -> DateTime<FixedOffset> {
+        let millis = self.last_active.load(Ordering::Relaxed);
+        self.connect_time.offset().timestamp_millis_opt(millis).single().unwrap_or(self.connect_time)
+    }
+
+    /// 距最后一次收到数据经过的秒数
+    pub fn idle_seconds(&self, now: &DateTime<FixedOffset>) -> i64 {
+        (now.timestamp_millis() - self.last_active.load(Ordering::Relaxed)) / 1000
+    }
+
+    /// 将消息放入发送队列，队列溢出且策略为断开时关闭连接
+    pub fn send(&self, message: String) -> EnqueueResult {
+        enqueue(self.id, &self.queue, &self.stats, &self.closer, message)
+    }
+
+    /// 分配序号后推送发给账号的消息，并记录到待确认列表，超时未确认时重发，超过重发上限后转入死信
+    pub fn push(&self, message: &SocketPushDTO, waiter: Option<oneshot::Sender<bool>>) -> EnqueueResult {
+        let (message, frame) = self.assign_seq(message);
+        let id = message.id.clone();
+        // 先登记再发送，避免客户端确认先于登记到达
+        self.pending_acks.lock().unwrap().insert(id.clone(), PendingAck {
+            message,
+            frame: frame.clone(),
+            attempts: 0,
+            sent_time: Instant::now(),
+            waiter,
+        });
+        let result = self.send(frame);
+        if !result.is_queued() {
+            self.pending_acks.lock().unwrap().remove(&id);
+        }
+        result
+    }
+
+    /// 分配序号后推送广播、组播或主题消息，不记录到待确认列表
+    pub fn push_untracked(&self, message: &SocketPushDTO) -> EnqueueResult {
+        self.send(self.assign_seq(message).1)
+    }
+
+    /// 为消息分配当前连接的下一个序号，返回（带序号的消息，已编码的消息）
+    fn assign_seq(&self, message: &SocketPushDTO) -> (SocketPushDTO, String) {
+        let mut message = message.clone();
+        message.seq = self.sequence.fetch_add(1, Ordering::SeqCst) + 1;
+        let frame = SocketMessageDTO::push(&message).to_string();
+        (message, frame)
+    }
+
+    /// 客户端确认收到消息，返回该消息是否在待确认列表中
+    pub fn ack(&self, id: &str) -> bool {
+        match self.pending_acks.lock().unwrap().remove(id) {
+            Some(pending) => {
+                if let Some(waiter) = pending.waiter {
+                    let _ = waiter.send(true);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 重发超时未确认的消息，等待时间按 timeout * 2^重发次数 退避，超过重发上限的消息被移出并返回（由调用方转入死信）
+    pub fn retry_unacked(&self, timeout: Duration, max_retry: u32) -> Vec<SocketPushDTO> {
+        let mut expired = vec![];
+        let mut pending_acks = self.pending_acks.lock().unwrap();
+        let now = Instant::now();
+        let due: Vec<String> = pending_acks
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.sent_time) >= timeout * 2u32.pow(pending.attempts.min(16)))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in due {
+            let pending = pending_acks.get_mut(&id).unwrap();
+            if pending.attempts >= max_retry {
+                let pending = pending_acks.remove(&id).unwrap();
+                if let Some(waiter) = pending.waiter {
+                    let _ = waiter.send(false);
+                }
+                expired.push(pending.message);
+                continue;
+            }
+            pending.attempts += 1;
+            pending.sent_time = now;
+            // 与首次推送一样经 send 遵循溢出策略并计入统计；未能入队的消息仍留在待确认列表中，超过重发上限后移出
+            if !self.send(pending.frame.clone()).is_queued() {
+                log::warn!("客户端 {} 重发消息 {} 未能进入发送队列，已重发{}次", self.id, id, pending.attempts);
+            }
+        }
+        expired
+    }
+
+    /// 取出全部未确认的消息（连接断开时使用）
+    pub fn take_unacked(&self) -> Vec<SocketPushDTO> {
+        let mut pending_acks: Vec<PendingAck> = self.pending_acks.lock().unwrap().drain().map(|(_, pending)| pending).collect();
+        pending_acks.sort_by_key(|pending| pending.message.seq);
+        pending_acks.into_iter().map(|pending| pending.message).collect()
+    }
+
+    /// 以指定的调用id向客户端发起远程调用，返回接收客户端应答的接收端
+    pub fn call(&self, call_id: &str, method: &str, params: Value) -> Result<oneshot::Receiver<Result<Value>>> {
+        let (sender, receiver) = oneshot::channel();
+        // 先登记再发送，避免客户端应答先于登记到达
+        self.pending_calls.lock().unwrap().insert(call_id.to_string(), sender);
+        if !self.send(SocketMessageDTO::request(call_id, method, params).to_string()).is_queued() {
+            self.pending_calls.lock().unwrap().remove(call_id);
+            return Err(Error::from(format!("连接 {} 的发送队列已满", self.id)));
+        }
+        Ok(receiver)
+    }
+
+    /// 客户端应答远程调用，返回该调用是否仍在等待应答
+    pub fn respond(&self, call_id: &str, result: Result<Value>) -> bool {
+        match self.pending_calls.lock().unwrap().remove(call_id) {
+            Some(sender) => sender.send(result).is_ok(),
+            None => false,
+        }
+    }
+
+    /// 放弃等待远程调用的应答（调用超时时使用）
+    pub fn cancel_call(&self, call_id: &str) {
+        self.pending_calls.lock().unwrap().remove(call_id);
+    }
+
+    /// 关闭发送队列并断开连接
+    pub fn close(&self) {
+        self.queue.close();
+        self.closer.send_replace(true);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet};
+    use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use serde_json::json;
+    use tokio::sync::watch;
+    use crate::config::socket_outbound::{OutboundQueue, OverflowPolicy};
+    use crate::domain::dto::socket_client_info::{SocketClientInfo, SocketStats};
+    use crate::domain::dto::socket_push::SocketPushDTO;
+    use crate::util::date_time::DateUtils;
+
+    #[test]
+    fn test_retry_overflow() {
+        let now = DateUtils::now();
+        let client = SocketClientInfo {
+            id: 1,
+            closer: Arc::new(watch::channel(false).0),
+            queue: Arc::new(OutboundQueue::new(1, OverflowPolicy::DropNewest)),
+            account: Some("alice".to_string()),
+            device: None,
+            organize: None,
+            tags: HashSet::new(),
+            topics: HashSet::new(),
+            peer_addr: String::new(),
+            connect_time: now,
+            last_active: AtomicI64::new(now.timestamp_millis()),
+            sequence: AtomicU64::new(0),
+            pending_acks: Mutex::new(HashMap::new()),
+            pending_calls: Mutex::new(HashMap::new()),
+            stats: Arc::new(SocketStats::default()),
+        };
+        let message = SocketPushDTO::new("1", None, json!("hello"));
+        assert!(client.push(&message, None).is_queued());
+        // 队列已满，重发的消息按溢出策略丢弃并计入统计，仍等待下次重发
+        assert!(client.retry_unacked(Duration::ZERO, 1).is_empty());
+        assert_eq!(client.stats.dropped.load(Ordering::Relaxed), 1);
+        assert_eq!(client.pending_acks.lock().unwrap().len(), 1);
+        // 超过重发上限后移出
+        let expired = client.retry_unacked(Duration::ZERO, 1);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, message.id);
+        assert!(client.pending_acks.lock().unwrap().is_empty());
+
+        // 未能进入发送队列的推送不记录到待确认列表，广播等消息也不记录
+        assert!(!client.push(&SocketPushDTO::new("2", None, json!("hello")), None).is_queued());
+        assert!(!client.push_untracked(&SocketPushDTO::new("3", None, json!("hello"))).is_queued());
+        assert!(client.pending_acks.lock().unwrap().is_empty());
+        assert_eq!(client.sequence.load(Ordering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// socket 握手认证信息，作为连接建立后第一条 auth 消息的 payload
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SocketHandshakeDTO {
+    /// 会话token，与http接口请求头中的access_token一致
+    pub access_token: Option<String>,
+    /// 设备id，同一账号在多个设备登录时用于区分连接
+    pub device: Option<String>,
+    /// 连接标签，用于分组推送
+    pub tags: Option<Vec<String>>,
+}
@@ -0,0 +1,208 @@
+use std::fmt::{Display, Formatter};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::domain::dto::socket_push::SocketPushDTO;
+use crate::domain::vo::RespVO;
+use crate::util::constant::{BAD_REQUEST_ERROR_CODE, FAIL_CODE, SUCCESS_CODE};
+use crate::util::date_time::DateUtils;
+use crate::util::error::Error;
+use crate::util::result::Result;
+
+/// socket 消息类型
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SocketMessageType {
+    /// 客户端 -> 服务端：握手认证，payload 为 SocketHandshakeDTO
+    Auth,
+    /// 双向：心跳
+    Ping,
+    /// 双向：心跳应答
+    Pong,
+    /// 客户端 -> 服务端：订阅主题，to 为主题
+    Subscribe,
+    /// 客户端 -> 服务端：取消订阅主题，to 为主题
+    Unsubscribe,
+    /// 客户端 -> 服务端：确认收到推送，id 为推送消息的id
+    Ack,
+    /// 客户端 -> 服务端：发送消息给其他账号，to 为接收账号
+    Message,
+    /// 客户端 -> 服务端：业务事件，topic 为事件名称，由注册的 MessageHandler 处理
+    Event,
+    /// 服务端 -> 客户端：推送消息，客户端收到后需回复 ack
+    Push,
+    /// 服务端 -> 客户端：请求处理成功的应答，id 与请求相同，payload 为 RespVO
+    Reply,
+    /// 服务端 -> 客户端：请求处理失败或消息格式错误，payload 为 RespVO
+    Error,
+    /// 服务端 -> 客户端：服务即将停止，客户端应稍后重新连接
+    ServerClosing,
+    /// 服务端 -> 客户端：连接被服务端断开（如管理员踢下线、账号在其他设备登录），payload 为原因
+    Kick,
+    /// 双向：远程调用，id 为调用id，topic 为方法名，payload 为参数，收到方需回复相同id的 response
+    Request,
+    /// 双向：远程调用的应答，id 与请求相同，payload 为 RespVO
+    Response,
+}
+
+/// socket 协议的消息信封，客户端与服务端之间的每一帧都是一个信封
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SocketMessageDTO {
+    /// 消息类型
+    #[serde(rename = "type")]
+    pub message_type: SocketMessageType,
+    /// 消息id，应答消息使用请求消息的id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// 发送时间戳，单位：毫秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ts: Option<i64>,
+    /// 发送方账号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// 接收方，按消息类型可以是账号或主题
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    /// 推送消息的主题
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// 推送消息在连接内的序号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    /// 消息内容
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub payload: Value,
+}
+
+impl SocketMessageDTO {
+    pub fn new(message_type: SocketMessageType, payload: Value) -> Self {
+        Self {
+            message_type,
+            id: None,
+            ts: Some(DateUtils::now().timestamp_millis()),
+            from: None,
+            to: None,
+            topic: None,
+            seq: None,
+            payload,
+        }
+    }
+
+    /// 解析客户端发来的一帧
+    pub fn parse(frame: &str) -> Result<Self> {
+        match serde_json::from_str(frame) {
+            Ok(message) => Ok(message),
+            Err(e) => Err(Error::from((format!("消息格式错误:{}", e), BAD_REQUEST_ERROR_CODE))),
+        }
+    }
+
+    /// 把 payload 解析为指定类型
+    pub fn payload_as<T: DeserializeOwned>(&self) -> Result<T> {
+        match serde_json::from_value(self.payload.clone()) {
+            Ok(payload) => Ok(payload),
+            Err(e) => Err(Error::from((format!("消息内容格式错误:{}", e), BAD_REQUEST_ERROR_CODE))),
+        }
+    }
+
+    /// 根据处理结果构造应答消息，成功时为 reply，失败时为 error
+    pub fn reply<T>(id: Option<String>, result: &Result<T>) -> Self
+        where
+            T: Serialize + DeserializeOwned + Clone,
+    {
+        let message_type = match result {
+            Ok(_) => SocketMessageType::Reply,
+            Err(_) => SocketMessageType::Error,
+        };
+        let payload = serde_json::to_value(RespVO::from_result(result)).unwrap_or_default();
+        Self {
+            id,
+            ..Self::new(message_type, payload)
+        }
+    }
+
+    /// 构造错误应答
+    pub fn error(id: Option<String>, error: &Error) -> Self {
+        Self::reply::<String>(id, &Err(error.clone()))
+    }
+
+    /// 构造远程调用请求
+    pub fn request(id: &str, method: &str, params: Value) -> Self {
+        Self {
+            id: Some(id.to_string()),
+            topic: Some(method.to_string()),
+            ..Self::new(SocketMessageType::Request, params)
+        }
+    }
+
+    /// 根据调用结果构造远程调用的应答
+    pub fn response(id: Option<String>, result: &Result<Value>) -> Self {
+        Self {
+            message_type: SocketMessageType::Response,
+            ..Self::reply(id, result)
+        }
+    }
+
+    /// 解析远程调用应答中的调用结果，code 不为成功时返回对方的错误信息与错误码
+    pub fn response_result(&self) -> Result<Value> {
+        let resp: RespVO<Value> = self.payload_as()?;
+        match resp.code {
+            Some(SUCCESS_CODE) => Ok(resp.data.unwrap_or_default()),
+            code => Err(Error::from((resp.msg.unwrap_or_default(), code.unwrap_or(FAIL_CODE)))),
+        }
+    }
+
+    /// 把推送消息包装成信封
+    pub fn push(message: &SocketPushDTO) -> Self {
+        Self {
+            id: Some(message.id.clone()),
+            from: message.from.clone(),
+            topic: message.topic.clone(),
+            seq: Some(message.seq),
+            ..Self::new(SocketMessageType::Push, message.payload.clone())
+        }
+    }
+}
+
+impl Display for SocketMessageDTO {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+    use crate::domain::dto::socket_message::{SocketMessageDTO, SocketMessageType};
+    use crate::util::error::Error;
+
+    #[test]
+    fn test_envelope() {
+        let message = SocketMessageDTO::parse(r#"{"type":"message","id":"1","to":"admin","payload":{"text":"hi"}}"#).unwrap();
+        assert_eq!(message.message_type, SocketMessageType::Message);
+        assert_eq!(message.to.as_deref(), Some("admin"));
+        assert_eq!(message.payload, json!({"text": "hi"}));
+        // 未知类型以及非JSON内容都视为格式错误
+        assert!(SocketMessageDTO::parse(r#"{"type":"address"}"#).is_err());
+        assert!(SocketMessageDTO::parse("address:127.0.0.1").is_err());
+
+        let reply = SocketMessageDTO::error(message.id, &Error::from("failed"));
+        let value: Value = serde_json::from_str(&reply.to_string()).unwrap();
+        assert_eq!(value["type"], "error");
+        assert_eq!(value["id"], "1");
+        assert_eq!(value["payload"]["msg"], "failed");
+    }
+
+    #[test]
+    fn test_response() {
+        let request = SocketMessageDTO::parse(&SocketMessageDTO::request("7", "status", json!({"verbose": true})).to_string()).unwrap();
+        assert_eq!(request.message_type, SocketMessageType::Request);
+        assert_eq!(request.topic.as_deref(), Some("status"));
+
+        let ok = SocketMessageDTO::response(request.id.clone(), &Ok(json!({"battery": 80})));
+        assert_eq!(ok.message_type, SocketMessageType::Response);
+        assert_eq!(ok.response_result().unwrap(), json!({"battery": 80}));
+        // 对方返回的错误码原样保留
+        let failed = SocketMessageDTO::response(request.id, &Err(Error::from(("busy", -7))));
+        assert_eq!(failed.response_result().err().unwrap().to_string(), "-7:busy");
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::domain::dto::socket_client_info::SocketClientInfo;
+
+/// socket 分组推送数据传输层，多个过滤条件同时指定时需全部满足
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SocketMulticastDTO {
+    /// 所属组织
+    pub organize_id: Option<u64>,
+    /// 账号列表，命中其中任意一个即可
+    pub accounts: Option<Vec<String>>,
+    /// 连接标签，命中其中任意一个即可
+    pub tags: Option<Vec<String>>,
+    /// 推送的消息内容
+    pub payload: Option<Value>,
+}
+
+impl SocketMulticastDTO {
+    /// 判断连接是否满足全部过滤条件
+    pub fn matches(&self, client: &SocketClientInfo) -> bool {
+        if let Some(organize_id) = self.organize_id {
+            if client.organize != Some(organize_id) {
+                return false;
+            }
+        }
+        if let Some(accounts) = &self.accounts {
+            match &client.account {
+                Some(account) if accounts.contains(account) => {}
+                _ => return false,
+            }
+        }
+        if let Some(tags) = &self.tags {
+            if !tags.iter().any(|tag| client.tags.contains(tag)) {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 服务端推送给socket客户端的消息，发送时包装成 push 类型的信封，客户端收到后需回复 ack
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SocketPushDTO {
+    /// 消息id，同一条消息推送到多个连接时id相同
+    pub id: String,
+    /// 连接内的消息序号，从1开始递增，由发送时分配
+    #[serde(default)]
+    pub seq: u64,
+    /// 主题，仅发布订阅的消息携带
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// 发送方账号，仅客户端之间互发的消息携带
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// 消息内容
+    pub payload: Value,
+}
+
+impl SocketPushDTO {
+    /// id 由 SocketServer::message_id 生成，同一集群内不重复
+    pub fn new(id: &str, topic: Option<&str>, payload: Value) -> Self {
+        Self {
+            id: id.to_string(),
+            seq: 0,
+            topic: topic.map(|item| item.to_string()),
+            from: None,
+            payload,
+        }
+    }
+}
+
+/// 推送socket消息时的查询参数
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SocketSendQueryDTO {
+    /// 等待客户端确认的秒数
+    pub wait_ack: Option<u64>,
+}
@@ -1,4 +1,5 @@
 pub mod user;
+pub mod socket;
 
 /// 响应模块
 
@@ -0,0 +1,137 @@
+use std::sync::atomic::Ordering;
+use serde::{Deserialize, Serialize};
+use crate::domain::dto::socket_client_info::SocketClientInfo;
+use crate::util::constant::FORMAT_Y_M_D_H_M_S;
+
+/// socket 消息的投递状态
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryState {
+    /// 客户端已确认收到
+    Delivered,
+    /// 已进入待发送队列
+    Queued,
+    /// 客户端不在线，消息已转入离线存储，待重新连接后补发
+    Offline,
+}
+
+/// socket 消息投递结果展示层
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SocketDeliveryVO {
+    /// 消息id
+    pub message_id: String,
+    /// 接收消息的账号
+    pub account: String,
+    /// 投递状态
+    pub state: DeliveryState,
+    /// 当前节点上成功投递（或进入发送队列）的连接数
+    pub connections: u64,
+    /// 转发到的其他节点数
+    pub nodes: u64,
+}
+
+/// socket 广播（分组推送）结果展示层
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SocketFanoutVO {
+    /// 消息id
+    pub message_id: String,
+    /// 当前节点上满足条件的连接数
+    pub matched: u64,
+    /// 当前节点上成功进入发送队列的连接数
+    pub queued: u64,
+    /// 转发到的其他节点数
+    pub nodes: u64,
+}
+
+/// socket 连接展示层
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SocketClientVO {
+    /// 连接id
+    pub id: u64,
+    /// 绑定的账号
+    pub account: Option<String>,
+    /// 绑定的设备id
+    pub device: Option<String>,
+    /// 账号所属组织
+    pub organize: Option<u64>,
+    /// 客户端声明的标签
+    pub tags: Vec<String>,
+    /// 已订阅的主题
+    pub topics: Vec<String>,
+    /// 客户端地址
+    pub peer_addr: String,
+    /// 建立连接的时间
+    pub connect_time: String,
+    /// 最后一次收到数据的时间
+    pub last_active_time: String,
+    /// 收发统计，仅查询单个连接时返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<SocketStatsVO>,
+}
+
+/// socket 连接收发统计展示层
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SocketStatsVO {
+    /// 收到的帧数
+    pub frames_in: u64,
+    /// 收到的字节数
+    pub bytes_in: u64,
+    /// 发送的帧数
+    pub frames_out: u64,
+    /// 发送的字节数
+    pub bytes_out: u64,
+    /// 发送队列溢出丢弃的消息数
+    pub dropped: u64,
+    /// 超过速率限制被限速的帧数
+    pub throttled: u64,
+    /// 发送队列中待发送的消息数
+    pub queued: u64,
+    /// 已推送但尚未确认的消息数
+    pub pending_acks: u64,
+}
+
+/// 断开socket连接的结果展示层
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SocketKickVO {
+    /// 当前节点上断开的连接数
+    pub connections: u64,
+    /// 转发到的其他节点数
+    pub nodes: u64,
+}
+
+impl From<&SocketClientInfo> for SocketClientVO {
+    fn from(arg: &SocketClientInfo) -> Self {
+        let mut tags: Vec<String> = arg.tags.iter().cloned().collect();
+        tags.sort();
+        let mut topics: Vec<String> = arg.topics.iter().cloned().collect();
+        topics.sort();
+        Self {
+            id: arg.id,
+            account: arg.account.clone(),
+            device: arg.device.clone(),
+            organize: arg.organize,
+            tags,
+            topics,
+            peer_addr: arg.peer_addr.clone(),
+            connect_time: arg.connect_time.format(FORMAT_Y_M_D_H_M_S).to_string(),
+            last_active_time: arg.last_active_time().format(FORMAT_Y_M_D_H_M_S).to_string(),
+            stats: None,
+        }
+    }
+}
+
+impl From<&SocketClientInfo> for SocketStatsVO {
+    fn from(arg: &SocketClientInfo) -> Self {
+        let stats = &arg.stats;
+        Self {
+            frames_in: stats.frames_in.load(Ordering::Relaxed),
+            bytes_in: stats.bytes_in.load(Ordering::Relaxed),
+            frames_out: stats.frames_out.load(Ordering::Relaxed),
+            bytes_out: stats.bytes_out.load(Ordering::Relaxed),
+            dropped: stats.dropped.load(Ordering::Relaxed),
+            throttled: stats.throttled.load(Ordering::Relaxed),
+            queued: arg.queue.len() as u64,
+            pending_acks: arg.pending_acks.lock().unwrap().len() as u64,
+        }
+    }
+}
@@ -7,7 +7,8 @@
 pub mod controller;
 pub mod config;
 pub mod service;
-mod domain;
+pub mod domain;
 pub mod util;
 mod dao;
-pub mod middleware;
\ No newline at end of file
+pub mod middleware;
+pub mod client;
\ No newline at end of file
@@ -1,10 +1,11 @@
-use rust_socket::controller::message_controller;
-use rust_socket::config::CONTEXT;
+use rust_socket::controller::{message_controller, socket_controller};
+use rust_socket::config::{CONTEXT, SOCKET_SERVER};
 use rust_socket::middleware::actix_interceptor::ActixInterceptor;
 use rust_socket::config::scheduler::Scheduler;
 
 use actix_web::{web, App,HttpServer};
-use rust_socket::config::socket_server::SocketServer;
+use rust_socket::config::socket_builder::SocketServerBuilder;
+use std::time::Duration;
 
 
 #[tokio::main]
@@ -27,14 +28,63 @@ async fn main() -> std::io::Result<()> {
                 web::scope("/message")
                     .service(message_controller::send_wechat_message)
                     .service(message_controller::send_mail_message)
+                    .service(message_controller::send_socket_message)
+                    .service(message_controller::broadcast_socket_message)
+                    .service(message_controller::multicast_socket_message)
+                    .service(message_controller::publish_socket_message)
+                    .service(message_controller::offline_message_list)
+                    .service(message_controller::offline_message_purge)
                     // .service(message_controller::user_add)
                     // .service(message_controller::user_update)
                     // .service(message_controller::user_detail)
                     // .service(message_controller::user_remove)
                     // .service(message_controller::user_page)
             )
-    }).bind(&CONTEXT.config.server_url)?.run();
-    let socket_server = SocketServer::init_socket_server(&CONTEXT.config.socket_url);
-    tokio::try_join!(socket_server, actix_server)?;
+            .service(
+                web::scope("/socket")
+                    .service(socket_controller::websocket)
+                    .service(socket_controller::client_page)
+                    .service(socket_controller::client_detail)
+                    .service(socket_controller::kick_client)
+                    .service(socket_controller::kick_account)
+                    .service(socket_controller::call_account)
+                    .service(socket_controller::call_client)
+            )
+    }).bind(&CONTEXT.config.server_url)?.disable_signals().run();
+    let actix_handle = actix_server.handle();
+    let mut actix_task = tokio::spawn(actix_server);
+    // http接口通过 SOCKET_SERVER 中的服务句柄访问连接注册表
+    let socket_server = SocketServerBuilder::from_config(&CONTEXT.config)
+        .start()
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    *SOCKET_SERVER.write().unwrap() = Some(socket_server.clone());
+    tokio::select! {
+        _ = shutdown_signal() => log::info!("[rust_socket] 收到停止信号，开始停止服务..."),
+        result = &mut actix_task => log::error!("[rust_socket] http服务意外退出:{:?}", result),
+    }
+    // 先停止socket服务并等待发送队列写完（WebSocket连接依附于http服务，需在http服务停止前断开），再停止http服务
+    socket_server.shutdown(Duration::from_secs(CONTEXT.config.socket_shutdown_timeout)).await;
+    actix_handle.stop(true).await;
+    Scheduler::stop_system_scheduler().await;
+    log::info!("[rust_socket] 服务已停止");
+    log::logger().flush();
     Ok(())
+}
+
+/// 等待 ctrl+c 或 SIGTERM 信号
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("[rust_socket] install SIGTERM handler fail");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
 }
\ No newline at end of file
@@ -5,12 +5,17 @@ use crate::config::CONTEXT;
 
 
 ///Whether the interface is in the whitelist
+/// 白名单按完整路径匹配，以 /** 结尾的条目匹配该路径及其下的所有子路径
 pub fn is_white_list_api(path: &str) -> bool {
     if path.eq("/") {
         return true;
     }
     for x in &CONTEXT.config.white_list_api {
-        if path.starts_with(x) {
+        let matched = match x.strip_suffix("/**") {
+            Some(prefix) => path == prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/')),
+            None => path == x,
+        };
+        if matched {
             return true;
         }
     }
@@ -55,4 +60,20 @@ pub async fn check_auth(token: &UserContext, path: &str) -> Result<(), crate::ut
     //     }
     // }
     // return Err(crate::util::error::Error::from("无权限访问!"));
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::middleware::auth::is_white_list_api;
+
+    /// 按 application.yml 中的白名单匹配，推送接口与前缀相同的其他路径都不在白名单中
+    #[test]
+    fn test_white_list_api() {
+        assert!(is_white_list_api("/"));
+        assert!(is_white_list_api("/socket/ws"));
+        assert!(is_white_list_api("/message/send/wechat/alice/welcome"));
+        assert!(!is_white_list_api("/socket/ws/other"));
+        assert!(!is_white_list_api("/message/send/wechatx/alice"));
+        assert!(!is_white_list_api("/message/send/socket/alice"));
+        assert!(!is_white_list_api("/message/send"));
+    }
+}
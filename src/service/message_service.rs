@@ -6,6 +6,8 @@ use crate::domain::dto::page::ExtendPageDTO;
 use crate::domain::dto::user::{UserDTO, UserPageDTO};
 use crate::domain::entity::User;
 use crate::domain::vo::user::UserVO;
+use crate::domain::vo::socket::SocketDeliveryVO;
+use crate::config::CONTEXT;
 use crate::{primary_rbatis_pool, util};
 use crate::util::result::Result;
 use crate::util::error::Error;
@@ -35,6 +37,18 @@ impl MessageService {
         Err(Error::from(("用户openid不能为空!", util::NOT_PARAMETER_CODE, )))
     }
 
+    /// 发送socket消息
+    /// param account  用户账号
+    /// param arg      消息内容
+    /// param wait_ack 等待客户端确认的秒数，为空时消息进入发送队列即返回；转发到其他节点的消息不等待确认
+    pub async fn send_socket_message(&self,account: &str,arg: &Value,wait_ack: Option<u64>)-> Result<SocketDeliveryVO>{
+        if account.is_empty() {
+            return Err(Error::from(("用户账号不能为空!", util::NOT_PARAMETER_CODE, )));
+        }
+        let server = CONTEXT.socket_server()?;
+        server.deliver(account, server.message(None, arg.clone()), wait_ack).await
+    }
+
     // /// 用户分页
     // pub async fn user_page(&self, arg: &UserPageDTO) -> Result<Page<UserVO>> {
     //     let mut extend = ExtendPageDTO {
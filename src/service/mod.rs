@@ -1,2 +1,4 @@
 /// 业务模块
-pub mod message_service;
\ No newline at end of file
+pub mod message_service;
+pub mod socket_service;
+pub mod offline_message_service;
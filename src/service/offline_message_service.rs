@@ -0,0 +1,91 @@
+use std::time::Duration;
+use crate::config::CONTEXT;
+use crate::domain::dto::socket_push::SocketPushDTO;
+use crate::util;
+use crate::util::constant::{SOCKET_DEAD_LETTER_PREFIX, SOCKET_OFFLINE_PREFIX};
+use crate::util::error::Error;
+use crate::util::result::Result;
+use serde_json::Value;
+
+/// socket 离线消息业务，账号不在线时消息暂存在redis中，账号完成握手后按顺序补发；
+/// 多次重发仍未被确认的消息转入死信
+pub struct OfflineMessageService {}
+
+impl OfflineMessageService {
+
+    fn cache_key(account: &str) -> String {
+        format!("{:}:{:}", SOCKET_OFFLINE_PREFIX, account)
+    }
+
+    fn dead_letter_key(account: &str) -> String {
+        format!("{:}:{:}", SOCKET_DEAD_LETTER_PREFIX, account)
+    }
+
+    async fn push(&self, key: &str, message: &SocketPushDTO) -> Result<i64> {
+        let value = serde_json::to_string(message).unwrap();
+        CONTEXT
+            .redis_client
+            .rpush_capped(
+                key,
+                &value,
+                CONTEXT.config.socket_offline_cap,
+                Duration::from_secs(CONTEXT.config.socket_offline_ttl),
+            )
+            .await
+    }
+
+    /// 保存一条离线消息，超出单账号上限时丢弃最旧的消息，返回当前待发送的消息数
+    pub async fn store(&self, account: &str, message: &SocketPushDTO) -> Result<i64> {
+        self.push(&OfflineMessageService::cache_key(account), message).await
+    }
+
+    /// 保存一条死信消息
+    pub async fn store_dead_letter(&self, account: &str, message: &SocketPushDTO) -> Result<i64> {
+        self.push(&OfflineMessageService::dead_letter_key(account), message).await
+    }
+
+    /// 取出账号的全部离线消息，取出后从redis中删除
+    pub async fn take(&self, account: &str) -> Result<Vec<SocketPushDTO>> {
+        let messages = CONTEXT
+            .redis_client
+            .take_list(&OfflineMessageService::cache_key(account))
+            .await?;
+        Ok(messages
+            .into_iter()
+            .filter_map(|message| match serde_json::from_str(&message) {
+                Ok(message) => Some(message),
+                Err(e) => {
+                    log::error!("账号:{} 的离线消息格式错误:{}", account, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// 查看账号待发送的离线消息
+    pub async fn list(&self, account: &str) -> Result<Vec<Value>> {
+        if account.is_empty() {
+            return Err(Error::from(("用户账号不能为空!", util::NOT_PARAMETER_CODE)));
+        }
+        let messages = CONTEXT
+            .redis_client
+            .lrange(&OfflineMessageService::cache_key(account))
+            .await?;
+        Ok(messages
+            .into_iter()
+            .map(|message| serde_json::from_str(&message).unwrap_or(Value::String(message)))
+            .collect())
+    }
+
+    /// 清空账号待发送的离线消息，返回删除的消息数
+    pub async fn purge(&self, account: &str) -> Result<u64> {
+        if account.is_empty() {
+            return Err(Error::from(("用户账号不能为空!", util::NOT_PARAMETER_CODE)));
+        }
+        let messages = CONTEXT
+            .redis_client
+            .take_list(&OfflineMessageService::cache_key(account))
+            .await?;
+        Ok(messages.len() as u64)
+    }
+}
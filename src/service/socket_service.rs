@@ -0,0 +1,167 @@
+use crate::config::socket_cluster::ClusterEvent;
+use crate::config::CONTEXT;
+use crate::domain::dto::page::ExtendPageDTO;
+use crate::domain::dto::socket_admin::{SocketCallDTO, SocketClientPageDTO, SocketKickDTO};
+use crate::domain::dto::socket_multicast::SocketMulticastDTO;
+use crate::domain::vo::socket::{SocketClientVO, SocketFanoutVO, SocketKickVO, SocketStatsVO};
+use crate::util;
+use crate::util::error::Error;
+use crate::util::page::Page;
+use crate::util::result::Result;
+use serde_json::Value;
+use std::time::Duration;
+
+/// socket 推送业务，启用集群时推送同时转发给其他节点，转发失败不影响当前节点上的推送
+pub struct SocketService {}
+
+impl SocketService {
+
+    /// 向所有在线的socket客户端广播消息
+    pub async fn broadcast(&self, arg: &Value) -> Result<SocketFanoutVO> {
+        let server = CONTEXT.socket_server()?;
+        let message = server.message(None, arg.clone());
+        let (matched, queued) = server.send_matching(&message, |_| true)?;
+        let nodes = server.relay_all(&ClusterEvent::Broadcast { message: message.clone() }).await;
+        log::info!("socket广播消息:{}，在线连接:{}，入队:{}，转发节点:{}", message.id, matched, queued, nodes);
+        Ok(SocketFanoutVO { message_id: message.id, matched, queued, nodes })
+    }
+
+    /// 按组织、账号或标签向部分socket客户端推送消息
+    pub async fn multicast(&self, arg: &SocketMulticastDTO) -> Result<SocketFanoutVO> {
+        let payload = match &arg.payload {
+            Some(payload) => payload.clone(),
+            None => return Err(Error::from(("推送内容payload不能为空!", util::NOT_PARAMETER_CODE))),
+        };
+        if arg.organize_id.is_none() && arg.accounts.is_none() && arg.tags.is_none() {
+            return Err(Error::from((
+                "组织、账号以及标签至少需要指定一个过滤条件!",
+                util::NOT_PARAMETER_CODE,
+            )));
+        }
+        let server = CONTEXT.socket_server()?;
+        let message = server.message(None, payload);
+        let (matched, queued) = server.send_matching(&message, |client| arg.matches(client))?;
+        // 转发时不携带消息内容，避免重复传输
+        let filter = SocketMulticastDTO { payload: None, ..arg.clone() };
+        let nodes = server.relay_all(&ClusterEvent::Multicast { filter, message: message.clone() }).await;
+        log::info!("socket分组推送消息:{}，匹配连接:{}，入队:{}，转发节点:{}", message.id, matched, queued, nodes);
+        Ok(SocketFanoutVO { message_id: message.id, matched, queued, nodes })
+    }
+
+    /// 向订阅了主题的socket客户端发布消息
+    pub async fn publish(&self, topic: &str, arg: &Value) -> Result<SocketFanoutVO> {
+        if topic.is_empty() {
+            return Err(Error::from(("主题topic不能为空!", util::NOT_PARAMETER_CODE)));
+        }
+        let server = CONTEXT.socket_server()?;
+        let message = server.message(Some(topic), arg.clone());
+        let (matched, queued) = server.publish(topic, &message)?;
+        let nodes = server.relay_all(&ClusterEvent::Publish { topic: topic.to_string(), message: message.clone() }).await;
+        log::info!("socket发布主题:{}，消息:{}，订阅连接:{}，入队:{}，转发节点:{}", topic, message.id, matched, queued, nodes);
+        Ok(SocketFanoutVO { message_id: message.id, matched, queued, nodes })
+    }
+
+    /// 分页查询当前节点上的socket连接，按连接id排序
+    pub async fn client_page(&self, arg: &SocketClientPageDTO) -> Result<Page<SocketClientVO>> {
+        let extend = ExtendPageDTO {
+            page_no: arg.page_no,
+            page_size: Some(arg.page_size.unwrap_or(10).clamp(1, 1000)),
+            begin_time: None,
+            end_time: None,
+        };
+        let registry = CONTEXT.socket_server()?.registry();
+        let clients = registry.read().unwrap();
+        let account = arg.account.as_deref().filter(|item| !item.is_empty());
+        let topic = arg.topic.as_deref().filter(|item| !item.is_empty());
+        let mut list: Vec<SocketClientVO> = clients
+            .values()
+            .filter(|client| account.is_none() || client.account.as_deref() == account)
+            .filter(|client| match topic {
+                Some(topic) => client.topics.contains(topic),
+                None => true,
+            })
+            .map(SocketClientVO::from)
+            .collect();
+        list.sort_by_key(|client| client.id);
+        let mut result = Page::<SocketClientVO>::page_query(list.len() as u64, &extend);
+        let skip = ((result.page_no - 1) * result.page_size) as usize;
+        result.records = Some(list.into_iter().skip(skip).take(result.page_size as usize).collect());
+        Ok(result)
+    }
+
+    /// 查询当前节点上socket连接的详情与收发统计
+    pub async fn client_detail(&self, id: u64) -> Result<SocketClientVO> {
+        let registry = CONTEXT.socket_server()?.registry();
+        let clients = registry.read().unwrap();
+        match clients.get(&id) {
+            Some(client) => Ok(SocketClientVO {
+                stats: Some(SocketStatsVO::from(client)),
+                ..SocketClientVO::from(client)
+            }),
+            None => Err(Error::from((format!("连接 {} 不存在", id), util::NOT_EXIST_CODE))),
+        }
+    }
+
+    /// 断开当前节点上的socket连接，客户端会收到携带原因的 kick 消息
+    pub async fn kick_client(&self, id: u64, arg: &SocketKickDTO) -> Result<SocketKickVO> {
+        let reason = SocketService::kick_reason(arg);
+        if !CONTEXT.socket_server()?.kick_client(id, &reason).await {
+            return Err(Error::from((format!("连接 {} 不存在", id), util::NOT_EXIST_CODE)));
+        }
+        log::info!("socket连接 {} 被管理员断开:{}", id, reason);
+        Ok(SocketKickVO { connections: 1, nodes: 0 })
+    }
+
+    /// 断开账号在所有节点上的socket连接，客户端会收到携带原因的 kick 消息
+    pub async fn kick_account(&self, account: &str, arg: &SocketKickDTO) -> Result<SocketKickVO> {
+        if account.is_empty() {
+            return Err(Error::from(("账号account不能为空!", util::NOT_PARAMETER_CODE)));
+        }
+        let reason = SocketService::kick_reason(arg);
+        let server = CONTEXT.socket_server()?;
+        let connections = server.kick(account, None, &reason).await;
+        let kick = ClusterEvent::Kick {
+            account: account.to_string(),
+            device: None,
+            reason: reason.clone(),
+        };
+        let nodes = server.relay(account, &kick).await;
+        log::info!("socket账号:{} 被管理员断开:{}，断开连接:{}，转发节点:{}", account, reason, connections, nodes);
+        Ok(SocketKickVO { connections, nodes })
+    }
+
+    /// 调用账号socket连接上的方法，等待客户端应答后返回应答内容；账号在其他节点上的连接由集群转发
+    pub async fn call_account(&self, account: &str, method: &str, arg: &SocketCallDTO, params: &Value) -> Result<Value> {
+        if account.is_empty() || method.is_empty() {
+            return Err(Error::from(("账号account与方法method不能为空!", util::NOT_PARAMETER_CODE)));
+        }
+        let device = arg.device.as_deref().filter(|item| !item.is_empty());
+        let timeout = SocketService::call_timeout(arg);
+        let result = CONTEXT.socket_server()?.call(account, device, method, params.clone(), timeout).await;
+        log::info!("socket远程调用账号:{} 设备:{} 的方法{}，结果:{}", account, device.unwrap_or_default(), method, result.is_ok());
+        result
+    }
+
+    /// 调用当前节点上socket连接的方法，等待客户端应答后返回应答内容
+    pub async fn call_client(&self, id: u64, method: &str, arg: &SocketCallDTO, params: &Value) -> Result<Value> {
+        if method.is_empty() {
+            return Err(Error::from(("方法method不能为空!", util::NOT_PARAMETER_CODE)));
+        }
+        let timeout = SocketService::call_timeout(arg);
+        let result = CONTEXT.socket_server()?.call_client(id, method, params.clone(), timeout).await;
+        log::info!("socket远程调用连接:{} 的方法{}，结果:{}", id, method, result.is_ok());
+        result
+    }
+
+    /// 等待客户端应答的时间，最长60秒，避免http请求长时间挂起
+    fn call_timeout(arg: &SocketCallDTO) -> Duration {
+        Duration::from_secs(arg.timeout.unwrap_or(CONTEXT.config.socket_call_timeout).clamp(1, 60))
+    }
+
+    fn kick_reason(arg: &SocketKickDTO) -> String {
+        match arg.reason.as_deref().map(str::trim) {
+            Some(reason) if !reason.is_empty() => reason.to_string(),
+            _ => "连接被管理员断开".to_string(),
+        }
+    }
+}
@@ -48,4 +48,14 @@ pub const BROWSER_PLATFORM_TTL: u64 = 3600;
 /// 定义桌面端token的过期时间，单位：秒
 pub const DESKTOP_PLATFORM_TTL: u64 = 604800;
 /// 定义防重复请求token的缓存前缀
-pub const WECHAT_ACCESS_TOKEN_PREFIX: &str = "wechat_access_token";
\ No newline at end of file
+pub const WECHAT_ACCESS_TOKEN_PREFIX: &str = "wechat_access_token";
+/// 定义socket离线消息的缓存前缀
+pub const SOCKET_OFFLINE_PREFIX: &str = "socket_offline";
+/// 定义socket死信消息的缓存前缀
+pub const SOCKET_DEAD_LETTER_PREFIX: &str = "socket_dead_letter";
+/// 定义socket账号所在节点的缓存前缀
+pub const SOCKET_PRESENCE_PREFIX: &str = "socket_presence";
+/// 定义socket节点私有频道的前缀
+pub const SOCKET_NODE_CHANNEL_PREFIX: &str = "socket_node";
+/// 定义socket集群广播频道
+pub const SOCKET_CLUSTER_CHANNEL: &str = "socket_cluster";